@@ -1,9 +1,15 @@
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
 
 use mongodb::error::Error as MongoDbError;
 use thiserror::Error;
 
-use crate::migration_record::MigrationRecord;
+use crate::{
+    migration_record::MigrationRecord,
+    migrator::{
+        checksum::ChecksumMismatch, consistency::OutOfOrderMigration, lock::MigrationLock,
+        store::StoreError,
+    },
+};
 
 #[derive(Error, Debug, Clone)]
 pub enum MigrationExecution {
@@ -22,7 +28,7 @@ pub enum MigrationExecution {
     InProgressStatusNotSaved {
         migration_id: String,
         next_not_executed_migrations_ids: Vec<String>,
-        additional_info: MongoDbError,
+        additional_info: StoreError,
     },
     #[error("Migration - {migration_id} has finished with the status: {migration_status}
 	    but the migration_record attempted to be writted as a migration result into migrations collections
@@ -42,16 +48,77 @@ pub enum MigrationExecution {
     FinishedButNotSavedDueMongoError {
         migration_id: String,
         migration_status: String,
-        additional_info: MongoDbError,
+        additional_info: StoreError,
         next_not_executed_migrations_ids: Vec<String>,
     },
     #[error(
         "Migration wasn't completed successfully - {migration_id}
-	 due to that, following it migrations: {next_not_executed_migrations_ids:?} weren't executed"
+	 due to that, following it migrations: {next_not_executed_migrations_ids:?} weren't executed
+	 error: {error:#}"
     )]
     FinishedAndSavedAsFail {
         migration_id: String,
         next_not_executed_migrations_ids: Vec<String>,
+        error: Arc<anyhow::Error>,
+    },
+    #[error(
+        "Migration was cancelled since it wasn't completed within {timeout:?} - {migration_id}
+	 due to that, following it migrations: {next_not_executed_migrations_ids:?} weren't executed"
+    )]
+    TimedOut {
+        migration_id: String,
+        timeout: Duration,
+        next_not_executed_migrations_ids: Vec<String>,
+    },
+    #[error(
+        "Migration - {migration_id} is transactional but the server doesn't support transactions,
+	 they are available only on a replica set or a sharded cluster
+	 due to that, following it migrations: {next_not_executed_migrations_ids:?} weren't executed"
+    )]
+    TransactionsNotSupported {
+        migration_id: String,
+        next_not_executed_migrations_ids: Vec<String>,
+    },
+    #[error(
+        "Migration - {migration_id} is transactional but its record can't be committed in its transaction,
+	 since migrations state is kept by a custom store or by a state db of another client
+	 due to that, following it migrations: {next_not_executed_migrations_ids:?} weren't executed"
+    )]
+    TransactionsNotSupportedByStore {
+        migration_id: String,
+        next_not_executed_migrations_ids: Vec<String>,
+    },
+    #[error(
+        "Failed to start a transaction for the migration - {migration_id}
+	 due to that, following it migrations: {next_not_executed_migrations_ids:?} weren't executed
+	 additional_info: {additional_info}"
+    )]
+    TransactionNotStarted {
+        migration_id: String,
+        next_not_executed_migrations_ids: Vec<String>,
+        additional_info: MongoDbError,
+    },
+    #[error(
+        "Failed to save the state of the migration - {migration_id} set by hand
+	 additional_info: {additional_info}"
+    )]
+    ManualChangeNotSaved {
+        migration_id: String,
+        additional_info: StoreError,
+    },
+    #[error(
+        "Migrations weren't executed since some migrations were in progress when their migrator crashed:
+	 {migrations_ids:?}, resolve them with repair()"
+    )]
+    StaleMigrationsFound { migrations_ids: Vec<String> },
+    #[error(
+        "The record of the migration - {migration_id} was modified by someone else, expected version: {expected_version}
+	 due to that, following it migrations: {next_not_executed_migrations_ids:?} weren't executed"
+    )]
+    MigrationRecordModifiedConcurrently {
+        migration_id: String,
+        expected_version: i64,
+        next_not_executed_migrations_ids: Vec<String>,
     },
     #[error(
         "Migrations weren't executed since there are several migrations with duplicated ids(id, indices vec):
@@ -62,4 +129,103 @@ pub enum MigrationExecution {
     },
     #[error("Single migration for execution wasn't found in migrations vec: {migration_id:?}")]
     MigrationFromVecNotFound { migration_id: String },
+    #[error(
+        "Migrations weren't executed since some migrations depend on migrations
+	 which weren't passed(id, missing dependencies ids): {missing:?}"
+    )]
+    MissingDependencies {
+        missing: BTreeMap<String, Vec<String>>,
+    },
+    #[error("Migrations weren't executed since their dependencies form a cycle: {cycle:?}")]
+    DependenciesCycle { cycle: Vec<String> },
+    #[error(
+        "Migrations weren't executed since already applied migrations were changed: {mismatches:?}"
+    )]
+    ChecksumsMismatch { mismatches: Vec<ChecksumMismatch> },
+    #[error(
+        "Migrations weren't executed since the migrations collection contains records
+	 which don't match any passed migration, e.g. renamed or deleted ones: {migrations_ids:?}"
+    )]
+    UnknownMigrationRecords { migrations_ids: Vec<String> },
+    #[error(
+        "Migrations weren't executed since never executed migrations are placed before applied ones:
+	 {out_of_order:?}"
+    )]
+    OutOfOrderMigrations {
+        out_of_order: Vec<OutOfOrderMigration>,
+    },
+    #[error(
+        "Migrations weren't executed since their state is written in the format {format_version}
+	 by a newer migrator, the format {supported_format_version} is supported"
+    )]
+    StateFormatNotSupported {
+        format_version: u32,
+        supported_format_version: u32,
+    },
+    #[error(
+        "The migrations state export can't be written or read, additional_info: {additional_info}"
+    )]
+    InvalidStateExport { additional_info: String },
+    #[error(
+        "Migrations state wasn't imported since records of the following migrations exist already:
+	 {migrations_ids:?}"
+    )]
+    ImportConflicts { migrations_ids: Vec<String> },
+    #[error(
+        "Failed to import the migrations state for the migration - {migration_id}
+	    additional_info: {additional_info}"
+    )]
+    StateNotImported {
+        migration_id: String,
+        additional_info: StoreError,
+    },
+    #[error("Failed to upgrade the migrations state format, additional_info: {additional_info}")]
+    StateNotUpgraded { additional_info: StoreError },
+    #[error(
+        "Failed to write the migrations history record for the migration - {migration_id}
+	    additional_info: {additional_info}"
+    )]
+    HistoryRecordNotSaved {
+        migration_id: String,
+        additional_info: StoreError,
+    },
+    #[error("Failed to read the migrations history, additional_info: {additional_info}")]
+    HistoryNotLoaded { additional_info: StoreError },
+    #[error(
+        "Migrations weren't executed since the migrations lock wasn't acquired by {owner}
+	    the lock is held by: {held_by:?}"
+    )]
+    LockNotAcquired {
+        owner: String,
+        held_by: Option<MigrationLock>,
+    },
+    #[error("Failed to read or write the migrations lock, additional_info: {additional_info}")]
+    LockOperationFailed { additional_info: MongoDbError },
+    #[error("Neither a migrations store nor a connection is set, so the state of migrations can't be kept")]
+    StoreNotSet,
+    #[error("Failed to read migration records, additional_info: {additional_info}")]
+    MigrationRecordsNotLoaded { additional_info: StoreError },
+    #[error(
+        "Migration - {migration_id} wasn't executed since migrations it's blocked by failed: {failed:?}"
+    )]
+    DependencyFailed {
+        migration_id: String,
+        failed: Vec<String>,
+    },
+    #[error(
+        "All migrations were attempted to be executed but some of them failed(id, cause):
+	 {}",
+        display_failures(.failures)
+    )]
+    TryAllFinishedWithFailures {
+        failures: Vec<(String, MigrationExecution)>,
+    },
+}
+
+fn display_failures(failures: &[(String, MigrationExecution)]) -> String {
+    failures
+        .iter()
+        .map(|(migration_id, cause)| format!("{migration_id}: {cause}"))
+        .collect::<Vec<String>>()
+        .join("\n\t ")
 }
@@ -71,7 +71,10 @@
 
 pub mod error;
 pub mod migration;
+pub mod migration_history;
 pub mod migration_record;
 pub mod migration_status;
 pub mod migrator;
+pub mod operation_type;
 pub mod server;
+pub mod state_metadata;
@@ -1,8 +1,10 @@
 //! In order to treat the entity as migrationable it should implement [`Migration`] trait
+use std::time::Duration;
+
 use anyhow::Result;
 use async_trait::async_trait;
 
-use crate::migrator::Env;
+use crate::migrator::{checksum, with_retries::RetryPolicy, Env};
 
 #[async_trait]
 pub trait Migration: Sync + Send {
@@ -16,6 +18,48 @@ pub trait Migration: Sync + Send {
         Ok(())
     }
 
+    /// How long up/down may be executed before it's cancelled and saved as timed out.
+    /// Blocking code can't be cancelled, e.g. prefer [`crate::migrator::shell::Shell::execute_async`]
+    fn timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Executes the migration in a transaction which is committed together with its successful record,
+    /// so a failed migration doesn't leave its writes half-applied.
+    /// Writes have to be made with [`Env::session`], transactions require a replica set or a sharded cluster
+    fn transactional(&self) -> bool {
+        false
+    }
+
+    /// Overrides the migrator's retry policy for this migration
+    fn retry_policy(&self) -> Option<RetryPolicy> {
+        None
+    }
+
+    /// Ids of migrations which have to be executed before this one.
+    /// Migrations are ordered by their dependencies first and by their position in the vec second
+    fn depends_on(&self) -> Vec<String> {
+        vec![]
+    }
+
+    /// Migrations of the same group without dependencies between them are executed concurrently
+    /// when [`crate::migrator::parallel::ParallelConfig`] is set, the same applies to migrations without a group
+    fn group(&self) -> Option<String> {
+        None
+    }
+
+    /// The script executed by the migration, e.g. JavaScript passed to [`crate::migrator::shell::Shell::execute`]
+    /// It's used in order to calculate the default [`Migration::checksum`]
+    fn script(&self) -> Option<String> {
+        None
+    }
+
+    /// Saved when the migration is applied so that later changes of already applied migration
+    /// are noticed. By default it's a hash of [`Migration::script`]
+    fn checksum(&self) -> Option<String> {
+        self.script().map(checksum::calc_checksum)
+    }
+
     /// A status about a migration will be stored in a db collection with the following document id
     /// We can pass an id manually otherwise it will be based on the type name so that uniqueness per project
     /// is guaranteed out of the box
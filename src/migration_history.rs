@@ -0,0 +1,91 @@
+//! [`MigrationHistoryRecord`] describes the document which will be appended
+//! to the migrations history collection on every up/down attempt.  
+//! Unlike [`MigrationRecord`] which reflects only the latest state of a migration
+//! these documents are never overwritten by the next runs
+
+use bson::oid::ObjectId;
+use chrono::Utc;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    migration_record::{MigrationRecord, MigrationRecordError},
+    migration_status::MigrationStatus,
+    operation_type::{ManualOperation, OperationType},
+};
+
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct MigrationHistoryRecord {
+    pub _id: ObjectId,
+    /// Id of the migration the attempt was made for
+    pub migration_id: String,
+    /// All attempts made during the same up/down call share the same run id
+    pub run_id: String,
+    pub direction: OperationType,
+    /// Starts from 1, the next ones are retries within the same run
+    pub attempt: u32,
+    pub start_date: Option<chrono::DateTime<Utc>>,
+    pub end_date: Option<chrono::DateTime<Utc>>,
+    #[serde(default)]
+    pub status: MigrationStatus,
+    pub duration: Option<i64>,
+    pub error: Option<MigrationRecordError>,
+    /// Present when the state was set by hand instead of executing the migration
+    #[serde(default)]
+    pub manual: Option<ManualOperation>,
+    /// The same as [`MigrationRecord::target`]
+    #[serde(default)]
+    pub target: Option<String>,
+}
+
+impl MigrationHistoryRecord {
+    pub fn attempt_start(
+        migration_record: &MigrationRecord,
+        run_id: String,
+        direction: OperationType,
+        attempt: u32,
+    ) -> Self {
+        MigrationHistoryRecord {
+            _id: ObjectId::new(),
+            migration_id: migration_record._id.clone(),
+            run_id,
+            direction,
+            attempt,
+            start_date: migration_record.start_date,
+            end_date: None,
+            status: migration_record.status.clone(),
+            duration: None,
+            error: None,
+            manual: None,
+            target: migration_record.target.clone(),
+        }
+    }
+
+    /// [`ManualOperation::Forget`] is saved as [`OperationType::Down`] since the migration becomes pending,
+    /// the rest as [`OperationType::Up`]
+    pub fn manual_change(
+        migration_record: &MigrationRecord,
+        run_id: String,
+        manual_operation: ManualOperation,
+    ) -> Self {
+        MigrationHistoryRecord {
+            direction: if manual_operation == ManualOperation::Forget {
+                OperationType::Down
+            } else {
+                OperationType::Up
+            },
+            manual: Some(manual_operation),
+            ..Self::attempt_start(migration_record, run_id, OperationType::Up, 1)
+                .attempt_finished(migration_record)
+        }
+    }
+
+    pub fn attempt_finished(self, migration_record: &MigrationRecord) -> Self {
+        MigrationHistoryRecord {
+            end_date: migration_record.end_date,
+            status: migration_record.status.clone(),
+            duration: migration_record.duration,
+            error: migration_record.error.clone(),
+            ..self
+        }
+    }
+}
@@ -3,19 +3,75 @@
 //! It contains all useful attributes which might be used in order
 //! to understand the current state of a particular migration
 
+use std::backtrace::BacktraceStatus;
+
 use chrono::DateTime;
 use chrono::Utc;
 use serde_derive::{Deserialize, Serialize};
 
-use crate::migration_status::MigrationStatus;
+use crate::{migration_status::MigrationStatus, operation_type::ManualOperation};
 
 #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
 pub struct MigrationRecord {
     pub _id: String,
     pub start_date: Option<chrono::DateTime<Utc>>,
     pub end_date: Option<chrono::DateTime<Utc>>,
+    #[serde(default)]
     pub status: MigrationStatus,
     pub duration: Option<i64>,
+    /// Why the migration has failed, present only for failed migrations
+    #[serde(default)]
+    pub error: Option<MigrationRecordError>,
+    /// [`crate::migration::Migration::checksum`] of the applied migration
+    #[serde(default)]
+    pub checksum: Option<String>,
+    /// How many times up or down of the migration was started, across all runs
+    #[serde(default)]
+    pub attempts: u32,
+    /// Incremented on every write so that a record modified by someone else isn't overwritten
+    #[serde(default)]
+    pub version: i64,
+    /// The migrator which executes the in progress migration
+    #[serde(default)]
+    pub owner: Option<String>,
+    /// Regularly updated by the owner while the migration is in progress
+    #[serde(default)]
+    pub heartbeat_at: Option<chrono::DateTime<Utc>>,
+    /// Present when the state was set by hand instead of executing the migration
+    #[serde(default)]
+    pub manual: Option<ManualOperation>,
+    /// Name of the database the migration was applied to,
+    /// absent when migrations are executed without a connection
+    #[serde(default)]
+    pub target: Option<String>,
+}
+
+/// An error returned by a migration, saved in a form which is readable
+/// right from the migrations collection
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct MigrationRecordError {
+    /// The outermost error message
+    pub message: String,
+    /// Messages of the error and all its causes, from the outermost to the root cause
+    pub chain: Vec<String>,
+    /// Present only when a backtrace was captured, e.g. with RUST_BACKTRACE=1
+    pub backtrace: Option<String>,
+}
+
+impl From<&anyhow::Error> for MigrationRecordError {
+    fn from(error: &anyhow::Error) -> Self {
+        let backtrace = error.backtrace();
+
+        MigrationRecordError {
+            message: error.to_string(),
+            chain: error.chain().map(|cause| cause.to_string()).collect(),
+            backtrace: if backtrace.status() == BacktraceStatus::Captured {
+                Some(backtrace.to_string())
+            } else {
+                None
+            },
+        }
+    }
 }
 
 impl MigrationRecord {
@@ -26,6 +82,68 @@ impl MigrationRecord {
             end_date: None,
             status: MigrationStatus::InProgress,
             duration: None,
+            error: None,
+            checksum: None,
+            attempts: 1,
+            version: 1,
+            owner: None,
+            heartbeat_at: None,
+            manual: None,
+            target: None,
+        }
+    }
+
+    pub fn applied_to(self, target: Option<String>) -> Self {
+        MigrationRecord { target, ..self }
+    }
+
+    pub fn owned_by(self, owner: String) -> Self {
+        MigrationRecord {
+            owner: Some(owner),
+            heartbeat_at: self.start_date,
+            ..self
+        }
+    }
+
+    /// Continues counters of the record saved by the previous attempt if there is one
+    pub fn after(self, previous: Option<&MigrationRecord>) -> Self {
+        match previous {
+            Some(previous) => MigrationRecord {
+                attempts: previous.attempts + 1,
+                version: previous.version + 1,
+                ..self
+            },
+            None => self,
+        }
+    }
+
+    pub fn changed_manually(self, manual_operation: ManualOperation) -> Self {
+        MigrationRecord {
+            manual: Some(manual_operation),
+            ..self
+        }
+    }
+
+    /// Records written before [`crate::state_metadata::STATE_FORMAT_VERSION`] 2 don't have
+    /// a version, attempts and a target
+    pub fn is_legacy(&self) -> bool {
+        self.version == 0
+    }
+
+    /// Fills fields missing in a legacy record, a legacy record was made by a single attempt
+    pub fn upgraded(self, target: Option<String>) -> Self {
+        MigrationRecord {
+            attempts: self.attempts.max(1),
+            version: self.version + 1,
+            target: self.target.or(target),
+            ..self
+        }
+    }
+
+    pub fn next_version(self) -> Self {
+        MigrationRecord {
+            version: self.version + 1,
+            ..self
         }
     }
 
@@ -36,6 +154,36 @@ impl MigrationRecord {
             end_date: Some(end_date),
             status: MigrationStatus::Success,
             duration: Some(self.calc_migration_duration(end_date)),
+            error: None,
+            ..self
+        }
+    }
+
+    pub fn with_checksum(self, checksum: Option<String>) -> Self {
+        MigrationRecord { checksum, ..self }
+    }
+
+    pub fn migration_rolled_back(self) -> Self {
+        let end_date = Utc::now();
+
+        MigrationRecord {
+            end_date: Some(end_date),
+            status: MigrationStatus::RolledBack,
+            duration: Some(self.calc_migration_duration(end_date)),
+            error: None,
+            ..self
+        }
+    }
+
+    /// The migration stays applied, so it keeps the checksum of the applied migration
+    pub fn migration_rollback_failed(self, error: Option<&anyhow::Error>) -> Self {
+        let end_date = Utc::now();
+
+        MigrationRecord {
+            end_date: Some(end_date),
+            status: MigrationStatus::RollbackFailed,
+            duration: Some(self.calc_migration_duration(end_date)),
+            error: error.map(Into::into),
             ..self
         }
     }
@@ -51,11 +199,39 @@ impl MigrationRecord {
         }
     }
 
+    pub fn migration_timed_out(self) -> Self {
+        let end_date = Utc::now();
+
+        MigrationRecord {
+            end_date: Some(end_date),
+            status: MigrationStatus::TimedOut,
+            duration: Some(self.calc_migration_duration(end_date)),
+            ..self
+        }
+    }
+
+    pub fn migration_interrupted(self) -> Self {
+        MigrationRecord {
+            end_date: Some(Utc::now()),
+            status: MigrationStatus::Interrupted,
+            ..self
+        }
+    }
+
+    /// The same as [`MigrationRecord::migration_failed`] but also keeps the error
+    /// which caused the fail
+    pub fn migration_failed_with_error(self, error: &anyhow::Error) -> Self {
+        MigrationRecord {
+            error: Some(error.into()),
+            ..self.migration_failed()
+        }
+    }
+
     fn calc_migration_duration(&self, end_date: DateTime<Utc>) -> i64 {
-        if self.start_date.is_none() {
-            0
+        if let Some(start_date) = self.start_date {
+            (end_date.time() - start_date.time()).num_milliseconds()
         } else {
-            (end_date.time() - self.start_date.unwrap().time()).num_milliseconds()
+            0
         }
     }
 }
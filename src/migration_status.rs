@@ -1,7 +1,7 @@
 //! Describes a migration status
 use serde_derive::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Eq, PartialEq)]
 pub enum MigrationStatus {
     /// Migration which is running now
     InProgress,
@@ -9,4 +9,26 @@ pub enum MigrationStatus {
     Success,
     /// Migration was completed with an error
     Fail,
+    /// Migration was successfully rolled back, so it's pending again
+    RolledBack,
+    /// Rollback of the migration failed or was cancelled, so the migration is still applied
+    RollbackFailed,
+    /// Migration was cancelled since it was executed longer than its timeout
+    TimedOut,
+    /// Migration was in progress when its migrator crashed, it's unknown how much of it was applied
+    Interrupted,
+    /// The status is missing or written by a newer migrator, such migrations aren't executed
+    #[default]
+    #[serde(other)]
+    Unknown,
+}
+
+impl MigrationStatus {
+    /// Whether the migration is applied, so up skips it and down rolls it back
+    pub fn is_applied(&self) -> bool {
+        matches!(
+            self,
+            MigrationStatus::Success | MigrationStatus::RollbackFailed
+        )
+    }
 }
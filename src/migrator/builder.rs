@@ -0,0 +1,282 @@
+//! [`MigratorBuilder`] collects all migrator settings in any order and passes them to the runner.
+//! Migrations can be passed only after a connection or an in-memory store is set, it's checked at compile time
+use std::{sync::Arc, time::Duration};
+
+use mongodb::Database;
+use tokio_util::sync::CancellationToken;
+
+use super::{
+    checksum::ChecksumPolicy,
+    consistency::ConsistencyConfig,
+    env::{Extensions, Progress, ProgressHandler},
+    execution_strategy::ExecutionStrategy,
+    lock::{self, LockConfig},
+    observer::MigrationObserver,
+    parallel::ParallelConfig,
+    shell::ShellConfig,
+    stale::StaleConfig,
+    store::{MemoryMigrationStore, MigrationStore},
+    with_migrations_vec::WithMigrationsVec,
+    with_retries::RetryPolicy,
+    Migrator,
+};
+use crate::migration::Migration;
+
+/// `C` is [`Database`] once a connection is set, [`InMemory`] once an in-memory store is set
+/// and `()` before that
+#[derive(Clone)]
+pub struct MigratorBuilder<C = ()> {
+    db: C,
+    shell_config: Option<ShellConfig>,
+    retry_policy: RetryPolicy,
+    collection_name: Option<String>,
+    history_collection_name: Option<String>,
+    lock_config: Option<LockConfig>,
+    execution_strategy: ExecutionStrategy,
+    checksum_policy: ChecksumPolicy,
+    default_timeout: Option<Duration>,
+    cancellation_token: CancellationToken,
+    progress_handler: Option<ProgressHandler>,
+    extensions: Extensions,
+    observers: Vec<Arc<dyn MigrationObserver>>,
+    owner: String,
+    stale_config: StaleConfig,
+    consistency_config: ConsistencyConfig,
+    parallel_config: Option<ParallelConfig>,
+    store: Option<Arc<dyn MigrationStore>>,
+    state_db: Option<Database>,
+}
+
+impl MigratorBuilder {
+    /// Migrations get no connection in their [`crate::migrator::Env`], so it suits stub migrations,
+    /// e.g. in tests of migrations ordering
+    pub fn with_memory_store(mut self, store: MemoryMigrationStore) -> MigratorBuilder<InMemory> {
+        self.store = Some(Arc::new(store));
+        self.with_connection_state(InMemory)
+    }
+
+    pub fn new() -> Self {
+        Self {
+            db: (),
+            shell_config: None,
+            retry_policy: Default::default(),
+            collection_name: None,
+            history_collection_name: None,
+            lock_config: None,
+            execution_strategy: Default::default(),
+            checksum_policy: Default::default(),
+            default_timeout: None,
+            cancellation_token: Default::default(),
+            progress_handler: None,
+            extensions: Default::default(),
+            observers: vec![],
+            owner: lock::default_owner(),
+            stale_config: Default::default(),
+            consistency_config: Default::default(),
+            parallel_config: None,
+            store: None,
+            state_db: None,
+        }
+    }
+}
+
+/// Migrations are executed without a connection, their state is kept by [`MemoryMigrationStore`]
+#[derive(Clone, Debug)]
+pub struct InMemory;
+
+impl Default for MigratorBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C> MigratorBuilder<C> {
+    pub fn with_conn(self, db: Database) -> MigratorBuilder<Database> {
+        self.with_connection_state(db)
+    }
+
+    fn with_connection_state<D>(self, db: D) -> MigratorBuilder<D> {
+        MigratorBuilder {
+            db,
+            shell_config: self.shell_config,
+            retry_policy: self.retry_policy,
+            collection_name: self.collection_name,
+            history_collection_name: self.history_collection_name,
+            lock_config: self.lock_config,
+            execution_strategy: self.execution_strategy,
+            checksum_policy: self.checksum_policy,
+            default_timeout: self.default_timeout,
+            cancellation_token: self.cancellation_token,
+            progress_handler: self.progress_handler,
+            extensions: self.extensions,
+            observers: self.observers,
+            owner: self.owner,
+            stale_config: self.stale_config,
+            consistency_config: self.consistency_config,
+            parallel_config: self.parallel_config,
+            store: self.store,
+            state_db: self.state_db,
+        }
+    }
+
+    /// Makes [`crate::migrator::Env::shell`] available to migrations
+    pub fn with_shell_config(mut self, shell_config: ShellConfig) -> Self {
+        self.shell_config = Some(shell_config);
+        self
+    }
+
+    /// Retries any failed migration `count` times with the same delay
+    pub fn with_retries(self, count: usize, delay: Duration) -> Self {
+        self.with_retry_policy(RetryPolicy::fixed(count, delay))
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn with_collection_name<S: Into<String>>(mut self, collection_name: S) -> Self {
+        self.collection_name = Some(collection_name.into());
+        self
+    }
+
+    pub fn with_history_collection_name<S: Into<String>>(
+        mut self,
+        history_collection_name: S,
+    ) -> Self {
+        self.history_collection_name = Some(history_collection_name.into());
+        self
+    }
+
+    pub fn with_lock_config(mut self, lock_config: LockConfig) -> Self {
+        self.lock_config = Some(lock_config);
+        self
+    }
+
+    pub fn with_execution_strategy(mut self, execution_strategy: ExecutionStrategy) -> Self {
+        self.execution_strategy = execution_strategy;
+        self
+    }
+
+    pub fn with_checksum_policy(mut self, checksum_policy: ChecksumPolicy) -> Self {
+        self.checksum_policy = checksum_policy;
+        self
+    }
+
+    pub fn with_consistency_config(mut self, consistency_config: ConsistencyConfig) -> Self {
+        self.consistency_config = consistency_config;
+        self
+    }
+
+    /// Makes independent migrations be executed concurrently, see [`ParallelConfig`]
+    pub fn with_parallel_config(mut self, parallel_config: ParallelConfig) -> Self {
+        self.parallel_config = Some(parallel_config);
+        self
+    }
+
+    /// Keeps records, the history and the lock in the database instead of the target one
+    /// passed with [`MigratorBuilder::with_conn`]. Records of several targets are mixed
+    /// in a shared state database unless every target has its own collection name
+    pub fn with_state_db(mut self, state_db: Database) -> Self {
+        self.state_db = Some(state_db);
+        self
+    }
+
+    /// Keeps the state of migrations in the store instead of the migrations collections
+    pub fn with_store(mut self, store: impl MigrationStore + 'static) -> Self {
+        self.store = Some(Arc::new(store));
+        self
+    }
+
+    pub fn with_default_timeout(mut self, default_timeout: Duration) -> Self {
+        self.default_timeout = Some(default_timeout);
+        self
+    }
+
+    pub fn with_cancellation_token(mut self, cancellation_token: CancellationToken) -> Self {
+        self.cancellation_token = cancellation_token;
+        self
+    }
+
+    pub fn with_progress_handler(
+        mut self,
+        progress_handler: impl Fn(&Progress) + Send + Sync + 'static,
+    ) -> Self {
+        self.progress_handler = Some(Arc::new(progress_handler));
+        self
+    }
+
+    /// Observers are notified in the order they were added
+    pub fn with_observer(mut self, observer: impl MigrationObserver + 'static) -> Self {
+        self.observers.push(Arc::new(observer));
+        self
+    }
+
+    /// Identifies the migrator in records of migrations it executes
+    pub fn with_owner<S: Into<String>>(mut self, owner: S) -> Self {
+        self.owner = owner.into();
+        self
+    }
+
+    pub fn with_stale_config(mut self, stale_config: StaleConfig) -> Self {
+        self.stale_config = stale_config;
+        self
+    }
+
+    pub fn with_extension<T: Send + Sync + 'static>(mut self, value: T) -> Self {
+        self.extensions.insert(value);
+        self
+    }
+}
+
+impl MigratorBuilder<Database> {
+    pub fn with_migrations_vec(self, migrations: Vec<Box<dyn Migration>>) -> WithMigrationsVec {
+        let db = Some(self.db.clone());
+        self.into_runner(db, migrations)
+    }
+
+    pub fn build(self, migrations: Vec<Box<dyn Migration>>) -> Migrator {
+        self.with_migrations_vec(migrations).into()
+    }
+}
+
+impl MigratorBuilder<InMemory> {
+    pub fn with_migrations_vec(self, migrations: Vec<Box<dyn Migration>>) -> WithMigrationsVec {
+        self.into_runner(None, migrations)
+    }
+
+    pub fn build(self, migrations: Vec<Box<dyn Migration>>) -> Migrator {
+        self.with_migrations_vec(migrations).into()
+    }
+}
+
+impl<C> MigratorBuilder<C> {
+    fn into_runner(
+        self,
+        db: Option<Database>,
+        migrations: Vec<Box<dyn Migration>>,
+    ) -> WithMigrationsVec {
+        WithMigrationsVec {
+            db,
+            migrations,
+            shell_config: self.shell_config,
+            retry_policy: self.retry_policy,
+            collection_name: self.collection_name,
+            history_collection_name: self.history_collection_name,
+            lock_config: self.lock_config,
+            execution_strategy: self.execution_strategy,
+            checksum_policy: self.checksum_policy,
+            default_timeout: self.default_timeout,
+            cancellation_token: self.cancellation_token,
+            progress_handler: self.progress_handler,
+            extensions: self.extensions,
+            observers: self.observers,
+            owner: self.owner,
+            stale_config: self.stale_config,
+            consistency_config: self.consistency_config,
+            parallel_config: self.parallel_config,
+            store: self.store,
+            state_db: self.state_db,
+        }
+    }
+}
@@ -0,0 +1,28 @@
+//! Checksums allow to notice that an already applied migration was changed afterwards
+use sha2::{Digest, Sha256};
+
+/// What to do when a checksum of an applied migration differs from the stored one
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum ChecksumPolicy {
+    /// Don't compare checksums at all
+    Ignore,
+    /// Log mismatches and execute migrations
+    #[default]
+    Warn,
+    /// Don't execute migrations if there is at least one mismatch
+    Error,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ChecksumMismatch {
+    pub migration_id: String,
+    /// The checksum saved when the migration was applied
+    pub stored: String,
+    /// The checksum of the migration passed now
+    pub current: String,
+}
+
+/// Hex encoded sha256 of the passed script
+pub fn calc_checksum<S: AsRef<str>>(script: S) -> String {
+    format!("{:x}", Sha256::digest(script.as_ref().as_bytes()))
+}
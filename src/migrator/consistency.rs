@@ -0,0 +1,44 @@
+//! Compares the migrations collection with passed migrations in order to notice
+//! renamed, deleted or inserted before already applied migrations
+use serde_derive::{Deserialize, Serialize};
+
+/// What to do with an inconsistency between the migrations collection and passed migrations
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum ConsistencyPolicy {
+    /// Execute migrations as if nothing is wrong
+    Allow,
+    /// Log inconsistencies and execute migrations
+    #[default]
+    Warn,
+    /// Don't execute migrations if there is at least one inconsistency
+    Error,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ConsistencyConfig {
+    /// Applied to records which don't match any passed migration, e.g. renamed or deleted ones
+    pub unknown_records: ConsistencyPolicy,
+    /// Applied to never executed migrations placed before already applied ones
+    pub out_of_order: ConsistencyPolicy,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Eq, PartialEq)]
+pub struct ConsistencyReport {
+    /// Ids of records which don't match any passed migration
+    pub unknown_records: Vec<String>,
+    pub out_of_order: Vec<OutOfOrderMigration>,
+}
+
+/// A never executed migration placed before already applied ones
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct OutOfOrderMigration {
+    pub migration_id: String,
+    /// Applied migrations placed after it, in the execution order
+    pub applied_after: Vec<String>,
+}
+
+impl ConsistencyReport {
+    pub fn is_consistent(&self) -> bool {
+        self.unknown_records.is_empty() && self.out_of_order.is_empty()
+    }
+}
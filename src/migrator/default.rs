@@ -1,21 +1,4 @@
-use mongodb::Database;
+use super::builder::MigratorBuilder;
 
-use super::with_connection::WithConnection;
-
-pub struct DefaultMigrator {}
-
-impl DefaultMigrator {
-    pub fn new() -> Self {
-        Self {}
-    }
-
-    pub fn with_conn(self, db: Database) -> WithConnection {
-        WithConnection { db }
-    }
-}
-
-impl Default for DefaultMigrator {
-    fn default() -> Self {
-        Self::new()
-    }
-}
+/// The builder before a connection is set, e.g. `DefaultMigrator::new().with_conn(db)`
+pub type DefaultMigrator = MigratorBuilder;
@@ -0,0 +1,95 @@
+//! Orders migrations so that every migration is executed after migrations it depends on,
+//! migrations without dependencies between them keep the order of the migrations vec
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{error::MigrationExecution, migration::Migration};
+
+/// Expects migrations ids to be unique
+#[allow(clippy::result_large_err)]
+pub(crate) fn order_by_dependencies(
+    migrations: &[Box<dyn Migration>],
+) -> Result<Vec<&dyn Migration>, MigrationExecution> {
+    let indices = migrations
+        .iter()
+        .enumerate()
+        .map(|(index, migration)| (migration.get_id().to_string(), index))
+        .collect::<BTreeMap<String, usize>>();
+
+    let missing = migrations
+        .iter()
+        .map(|migration| {
+            (
+                migration.get_id().to_string(),
+                migration
+                    .depends_on()
+                    .into_iter()
+                    .filter(|dependency| !indices.contains_key(dependency))
+                    .collect::<Vec<String>>(),
+            )
+        })
+        .filter(|(_id, missing)| !missing.is_empty())
+        .collect::<BTreeMap<String, Vec<String>>>();
+
+    if !missing.is_empty() {
+        return Err(MigrationExecution::MissingDependencies { missing });
+    }
+
+    let dependencies = migrations
+        .iter()
+        .map(|migration| {
+            migration
+                .depends_on()
+                .iter()
+                .map(|dependency| indices[dependency])
+                .collect::<BTreeSet<usize>>()
+        })
+        .collect::<Vec<BTreeSet<usize>>>();
+
+    // Kahn's algorithm which always picks the ready migration placed first in the vec
+    let mut not_ordered = (0..migrations.len()).collect::<BTreeSet<usize>>();
+    let mut ordered = Vec::with_capacity(migrations.len());
+
+    while let Some(ready) = not_ordered
+        .iter()
+        .find(|&&index| dependencies[index].is_disjoint(&not_ordered))
+        .copied()
+    {
+        not_ordered.remove(&ready);
+        ordered.push(&*migrations[ready]);
+    }
+
+    if not_ordered.is_empty() {
+        Ok(ordered)
+    } else {
+        Err(MigrationExecution::DependenciesCycle {
+            cycle: find_cycle(migrations, &dependencies, &not_ordered),
+        })
+    }
+}
+
+/// Every not ordered migration depends on at least one not ordered migration,
+/// so following those dependencies leads to a cycle
+fn find_cycle(
+    migrations: &[Box<dyn Migration>],
+    dependencies: &[BTreeSet<usize>],
+    not_ordered: &BTreeSet<usize>,
+) -> Vec<String> {
+    let mut path = vec![*not_ordered.first().expect("not ordered migrations exist")];
+
+    loop {
+        let current = *path.last().expect("path isn't empty");
+        let next = *dependencies[current]
+            .intersection(not_ordered)
+            .next()
+            .expect("not ordered migration has a not ordered dependency");
+
+        if let Some(cycle_start) = path.iter().position(|&index| index == next) {
+            return path[cycle_start..]
+                .iter()
+                .map(|&index| migrations[index].get_id().to_string())
+                .collect();
+        }
+
+        path.push(next);
+    }
+}
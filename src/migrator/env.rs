@@ -0,0 +1,142 @@
+//! [`Env`] is everything a migration gets from the migrator in order to do its job
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    fmt,
+    sync::Arc,
+};
+
+use mongodb::{Client, ClientSession, Database};
+use serde_derive::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+
+use super::shell::Shell;
+
+#[derive(Clone, Default)]
+pub struct Env {
+    /// Absent when migrations are executed with [`crate::migrator::store::MemoryMigrationStore`]
+    pub db: Option<Database>,
+    /// The client of [`Env::db`], e.g. for admin commands or other databases
+    pub client: Option<Client>,
+    pub shell: Option<Shell>,
+    /// Present only for [`crate::migration::Migration::transactional`] migrations,
+    /// writes made with it are committed together with the migration record
+    pub session: Option<Arc<Mutex<ClientSession>>>,
+    pub migration_id: String,
+    /// Id of the current [`crate::migrator::with_migrations_vec::WithMigrationsVec::up`] or `down` call,
+    /// the same as [`crate::migration_history::MigrationHistoryRecord::run_id`]
+    pub run_id: String,
+    /// Cancelled when the migration timeout is elapsed or the migrator's token is cancelled,
+    /// long running migrations should check it and stop
+    pub cancellation: CancellationToken,
+    pub progress: ProgressReporter,
+    /// Values passed by the application, see [`Extensions`]
+    pub extensions: Extensions,
+}
+
+/// A progress of a long running migration
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct Progress {
+    pub migration_id: String,
+    pub run_id: String,
+    pub done: u64,
+    /// Unknown if the migration can't estimate its amount of work
+    pub total: Option<u64>,
+    pub message: Option<String>,
+}
+
+pub type ProgressHandler = Arc<dyn Fn(&Progress) + Send + Sync>;
+
+/// Traces reported progress and passes it to the handler set with
+/// [`crate::migrator::with_migrations_vec::WithMigrationsVec::set_progress_handler`]
+#[derive(Clone, Default)]
+pub struct ProgressReporter {
+    migration_id: String,
+    run_id: String,
+    handler: Option<ProgressHandler>,
+}
+
+impl ProgressReporter {
+    pub fn new(migration_id: String, run_id: String, handler: Option<ProgressHandler>) -> Self {
+        Self {
+            migration_id,
+            run_id,
+            handler,
+        }
+    }
+
+    pub fn report(&self, done: u64, total: Option<u64>, message: Option<String>) {
+        let progress = Progress {
+            migration_id: self.migration_id.clone(),
+            run_id: self.run_id.clone(),
+            done,
+            total,
+            message,
+        };
+
+        tracing::info!(
+            id = progress.migration_id,
+            run_id = progress.run_id,
+            done = progress.done,
+            total = progress.total,
+            message = progress.message
+        );
+
+        if let Some(handler) = &self.handler {
+            handler(&progress);
+        }
+    }
+}
+
+impl fmt::Debug for ProgressReporter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProgressReporter")
+            .field("migration_id", &self.migration_id)
+            .field("run_id", &self.run_id)
+            .field("handler", &self.handler.is_some())
+            .finish()
+    }
+}
+
+/// A map of values by their types so that an application can pass its config and services
+/// to migrations, at most one value per type is kept
+#[derive(Clone, Default)]
+pub struct Extensions {
+    values: HashMap<TypeId, Arc<dyn Any + Send + Sync>>,
+}
+
+impl Extensions {
+    /// Returns the previous value of the same type if there was one
+    pub fn insert<T: Send + Sync + 'static>(&mut self, value: T) -> Option<Arc<T>> {
+        self.values
+            .insert(TypeId::of::<T>(), Arc::new(value))
+            .and_then(|previous| previous.downcast().ok())
+    }
+
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.values
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref())
+    }
+
+    pub fn contains<T: Send + Sync + 'static>(&self) -> bool {
+        self.values.contains_key(&TypeId::of::<T>())
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+impl fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Extensions")
+            .field("len", &self.values.len())
+            .finish()
+    }
+}
@@ -0,0 +1,10 @@
+//! Describes how a migrator reacts on a failed migration
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum ExecutionStrategy {
+    /// Stop on the first failed migration, all next migrations are saved as failed
+    #[default]
+    FailFast,
+    /// Execute all migrations regardless of failed ones
+    /// and return all failures at the end. Suits independent migrations
+    TryAll,
+}
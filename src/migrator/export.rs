@@ -0,0 +1,97 @@
+//! [`StateExport`] is a snapshot of migration records, their history and the state metadata
+//! which can be saved to a JSON file and imported into another database,
+//! e.g. in order to clone an environment or move to a new cluster
+use chrono::{DateTime, Utc};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    error::MigrationExecution,
+    migration_history::MigrationHistoryRecord,
+    migration_record::MigrationRecord,
+    state_metadata::{StateMetadata, STATE_FORMAT_VERSION},
+};
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct StateExport {
+    /// [`STATE_FORMAT_VERSION`] of the exporting migrator
+    pub format_version: u32,
+    pub exported_at: DateTime<Utc>,
+    /// Absent when the exported state was never written by a migrator which knows about it
+    pub metadata: Option<StateMetadata>,
+    pub records: Vec<MigrationRecord>,
+    /// All attempts in the order they were started
+    pub history: Vec<MigrationHistoryRecord>,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum ExportFormat {
+    /// Relaxed Extended JSON, numbers are written as plain JSON numbers
+    #[default]
+    Json,
+    /// Canonical Extended JSON which keeps BSON types, e.g. 64-bit integers
+    ExtendedJson,
+}
+
+/// What to do with an imported record when a record of the same migration exists already
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum ConflictPolicy {
+    /// Keep the existing record
+    Skip,
+    /// Replace the existing record with the imported one
+    Overwrite,
+    /// Don't import anything if there is at least one conflict
+    #[default]
+    Fail,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Eq, PartialEq)]
+pub struct ImportReport {
+    /// Ids of migrations which didn't have a record
+    pub imported: Vec<String>,
+    pub overwritten: Vec<String>,
+    pub skipped: Vec<String>,
+    /// History records are appended regardless of conflicts, already imported ones are replaced
+    pub history_imported: usize,
+}
+
+impl StateExport {
+    pub fn new(
+        metadata: Option<StateMetadata>,
+        records: Vec<MigrationRecord>,
+        history: Vec<MigrationHistoryRecord>,
+    ) -> Self {
+        StateExport {
+            format_version: STATE_FORMAT_VERSION,
+            exported_at: Utc::now(),
+            metadata,
+            records,
+            history,
+        }
+    }
+
+    #[allow(clippy::result_large_err)]
+    pub fn to_json(&self, format: ExportFormat) -> Result<String, MigrationExecution> {
+        let bson = bson::to_bson(self).map_err(invalid)?;
+        let json = match format {
+            ExportFormat::Json => bson.into_relaxed_extjson(),
+            ExportFormat::ExtendedJson => bson.into_canonical_extjson(),
+        };
+
+        serde_json::to_string_pretty(&json).map_err(invalid)
+    }
+
+    /// Reads both formats of [`ExportFormat`]
+    #[allow(clippy::result_large_err)]
+    pub fn from_json(json: &str) -> Result<Self, MigrationExecution> {
+        let json = serde_json::from_str::<serde_json::Value>(json).map_err(invalid)?;
+        let bson = bson::Bson::try_from(json).map_err(invalid)?;
+
+        bson::from_bson(bson).map_err(invalid)
+    }
+}
+
+fn invalid(error: impl ToString) -> MigrationExecution {
+    MigrationExecution::InvalidStateExport {
+        additional_info: error.to_string(),
+    }
+}
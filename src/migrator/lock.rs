@@ -0,0 +1,198 @@
+//! A lock document which prevents several migrators(e.g. replicas of the same service)
+//! from running migrations at the same time.
+//! The lock is held by an owner for a ttl which is prolonged by a heartbeat
+//! so that a crashed owner doesn't block others forever
+use std::time::Duration;
+
+use bson::oid::ObjectId;
+use mongodb::{
+    error::{Error as MongoDbError, ErrorKind, WriteError, WriteFailure},
+    Collection,
+};
+use serde_derive::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+
+const LOCK_ID: &str = "migrations_lock";
+const DUPLICATE_KEY_ERROR_CODE: i32 = 11000;
+
+#[derive(Clone, Debug)]
+pub struct LockConfig {
+    /// Unique identifier of the migrator which holds the lock
+    pub owner: String,
+    /// How long the lock is considered as held after the latest heartbeat
+    pub ttl: Duration,
+    /// How often the lock is prolonged while migrations are running
+    pub heartbeat_interval: Duration,
+    /// What to do when the lock is held by someone else
+    pub wait: LockWait,
+}
+
+impl Default for LockConfig {
+    fn default() -> Self {
+        Self {
+            owner: default_owner(),
+            ttl: Duration::from_secs(60),
+            heartbeat_interval: Duration::from_secs(20),
+            wait: LockWait::WaitWithTimeout {
+                timeout: Duration::from_secs(300),
+                poll_interval: Duration::from_secs(1),
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum LockWait {
+    /// Return an error right away if the lock is held by someone else
+    FailFast,
+    /// Try to acquire the lock every `poll_interval` until `timeout` is elapsed
+    WaitWithTimeout {
+        timeout: Duration,
+        poll_interval: Duration,
+    },
+}
+
+/// The document stored in the lock collection while the lock is held
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct MigrationLock {
+    pub _id: String,
+    pub owner: String,
+    pub acquired_at: bson::DateTime,
+    pub heartbeat_at: bson::DateTime,
+    pub expires_at: bson::DateTime,
+}
+
+impl MigrationLock {
+    /// An expired lock can be taken over by any other owner
+    pub fn is_expired(&self) -> bool {
+        self.expires_at < bson::DateTime::now()
+    }
+}
+
+/// Keeps the lock prolonged until it's released
+pub(crate) struct LockGuard {
+    heartbeat: JoinHandle<()>,
+}
+
+impl Drop for LockGuard {
+    fn drop(&mut self) {
+        self.heartbeat.abort();
+    }
+}
+
+pub(crate) fn default_owner() -> String {
+    format!(
+        "{}-{}-{}",
+        std::env::var("HOSTNAME").unwrap_or_else(|_| "unknown".to_string()),
+        std::process::id(),
+        ObjectId::new().to_hex()
+    )
+}
+
+fn expires_at(ttl: Duration) -> bson::DateTime {
+    bson::DateTime::from_millis(bson::DateTime::now().timestamp_millis() + ttl.as_millis() as i64)
+}
+
+/// Returns `false` when the lock is held by someone else
+pub(crate) async fn try_acquire(
+    collection: &Collection<MigrationLock>,
+    config: &LockConfig,
+) -> Result<bool, MongoDbError> {
+    let now = bson::DateTime::now();
+
+    let res = collection
+        .update_one(
+            bson::doc! {
+                "_id": LOCK_ID,
+                "$or": [{"owner": &config.owner}, {"expires_at": {"$lt": now}}]
+            },
+            bson::doc! {"$set": {
+                "owner": &config.owner,
+                "acquired_at": now,
+                "heartbeat_at": now,
+                "expires_at": expires_at(config.ttl),
+            }},
+        )
+        .upsert(true)
+        .await;
+
+    match res {
+        Ok(_) => Ok(true),
+        // the lock document exists but it isn't ours and isn't expired,
+        // so the upsert attempted to insert the second one
+        Err(error) if is_duplicate_key_error(&error) => Ok(false),
+        Err(error) => Err(error),
+    }
+}
+
+/// An upsert which filter didn't match the existing document attempts to insert the second one
+pub(crate) fn is_duplicate_key_error(error: &MongoDbError) -> bool {
+    matches!(
+        *error.kind,
+        ErrorKind::Write(WriteFailure::WriteError(WriteError {
+            code: DUPLICATE_KEY_ERROR_CODE,
+            ..
+        }))
+    )
+}
+
+pub(crate) fn start_heartbeat(
+    collection: Collection<MigrationLock>,
+    config: LockConfig,
+) -> LockGuard {
+    let heartbeat = tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(config.heartbeat_interval).await;
+
+            let now = bson::DateTime::now();
+            let res = collection
+                .update_one(
+                    bson::doc! {"_id": LOCK_ID, "owner": &config.owner},
+                    bson::doc! {"$set": {"heartbeat_at": now, "expires_at": expires_at(config.ttl)}},
+                )
+                .await;
+
+            match res {
+                Ok(res) if res.matched_count == 0 => {
+                    tracing::warn!(message = "migrations lock was lost", owner = config.owner);
+                    break;
+                }
+                Ok(_) => {}
+                Err(error) => {
+                    tracing::warn!(
+                        message = "migrations lock heartbeat failed",
+                        owner = config.owner,
+                        error = error.to_string()
+                    );
+                }
+            }
+        }
+    });
+
+    LockGuard { heartbeat }
+}
+
+pub(crate) async fn release(
+    collection: &Collection<MigrationLock>,
+    owner: &str,
+) -> Result<(), MongoDbError> {
+    collection
+        .delete_one(bson::doc! {"_id": LOCK_ID, "owner": owner})
+        .await?;
+
+    Ok(())
+}
+
+pub(crate) async fn status(
+    collection: &Collection<MigrationLock>,
+) -> Result<Option<MigrationLock>, MongoDbError> {
+    collection.find_one(bson::doc! {"_id": LOCK_ID}).await
+}
+
+pub(crate) async fn force_release(
+    collection: &Collection<MigrationLock>,
+) -> Result<Option<MigrationLock>, MongoDbError> {
+    collection
+        .find_one_and_delete(bson::doc! {"_id": LOCK_ID})
+        .await
+}
@@ -1,29 +1,61 @@
 //! Migrator runs passed migrations - entities which implement [`Migration`] trait
+pub mod builder;
+pub mod checksum;
+pub mod consistency;
 pub mod default;
+mod dependencies;
+pub mod env;
+pub mod execution_strategy;
+pub mod export;
+pub mod lock;
+pub mod observer;
+pub mod parallel;
+pub mod plan;
+pub mod report;
 pub mod shell;
-pub mod with_connection;
+pub mod stale;
+pub mod store;
 pub mod with_migrations_vec;
 pub mod with_retries;
-pub mod with_shell_config;
-
-use mongodb::Database;
-
-use self::{
-    default::DefaultMigrator, shell::Shell, with_connection::WithConnection,
-    with_migrations_vec::WithMigrationsVec, with_retries::WithRetries,
-    with_shell_config::WithShellConfig,
-};
-
-pub enum Migrator {
-    DefaultMigrator(DefaultMigrator),
-    WithConnection(WithConnection),
-    WithMigrationsVec(WithMigrationsVec),
-    WithShellConfig(WithShellConfig),
-    WithRetries(WithRetries),
+
+use std::ops::{Deref, DerefMut};
+
+use self::with_migrations_vec::WithMigrationsVec;
+pub use self::{builder::MigratorBuilder, default::DefaultMigrator, env::Env};
+
+/// The single entry point of the migrator:
+/// `Migrator::builder().with_conn(db).with_retries(3, delay).build(migrations).up().await`.
+/// All operations of [`WithMigrationsVec`] are available on it
+pub struct Migrator {
+    runner: WithMigrationsVec,
+}
+
+impl Migrator {
+    pub fn builder() -> MigratorBuilder {
+        MigratorBuilder::new()
+    }
+
+    pub fn into_inner(self) -> WithMigrationsVec {
+        self.runner
+    }
+}
+
+impl From<WithMigrationsVec> for Migrator {
+    fn from(runner: WithMigrationsVec) -> Self {
+        Self { runner }
+    }
+}
+
+impl Deref for Migrator {
+    type Target = WithMigrationsVec;
+
+    fn deref(&self) -> &Self::Target {
+        &self.runner
+    }
 }
 
-#[derive(Clone, Default)]
-pub struct Env {
-    pub db: Option<Database>,
-    pub shell: Option<Shell>,
+impl DerefMut for Migrator {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.runner
+    }
 }
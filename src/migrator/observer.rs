@@ -0,0 +1,43 @@
+//! [`MigrationObserver`] is notified about the progress of a migrations run,
+//! e.g. in order to send notifications, collect metrics or write an audit log
+use async_trait::async_trait;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    error::MigrationExecution, migration_record::MigrationRecord, operation_type::OperationType,
+};
+
+/// A single [`crate::migrator::with_migrations_vec::WithMigrationsVec::up`] or `down` call
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct MigrationRun {
+    /// The same as [`crate::migration_history::MigrationHistoryRecord::run_id`]
+    pub run_id: String,
+    pub operation_type: OperationType,
+    /// Migrations which are going to be executed, in the execution order
+    pub migrations_ids: Vec<String>,
+}
+
+/// All callbacks do nothing by default, so only needed ones can be implemented.
+/// Callbacks are awaited by the migrator, so they shouldn't take long
+#[async_trait]
+pub trait MigrationObserver: Send + Sync {
+    /// Called when it's known which migrations are going to be executed
+    async fn before_all(&self, _run: &MigrationRun) {}
+
+    /// Called once per migration before its first attempt
+    async fn before_each(&self, _run: &MigrationRun, _migration_id: &str) {}
+
+    /// Called once per migration after its last attempt with the saved record,
+    /// it's not called when the record wasn't saved at all
+    async fn after_each(&self, _run: &MigrationRun, _record: &MigrationRecord) {}
+
+    /// Called once per migration which has failed after all its attempts.
+    /// It's also called with the error which has stopped the run before it was started,
+    /// e.g. a failed validation or a not acquired lock, then `before_all` isn't called
+    /// and the run has no migrations
+    async fn on_failure(&self, _run: &MigrationRun, _error: &MigrationExecution) {}
+
+    /// Called when the run is finished, even if it was stopped by a failed migration
+    /// or by an error before it was started
+    async fn after_all(&self, _run: &MigrationRun, _result: &Result<(), MigrationExecution>) {}
+}
@@ -0,0 +1,50 @@
+//! Splits planned migrations into batches which are executed concurrently when [`ParallelConfig`] is set.
+//! A migration joins the current batch when it has the same [`Migration::group`] as the batch
+//! and there is no dependency between it and any migration of the batch
+use crate::migration::Migration;
+
+/// Migrations without declared dependencies between them may be executed concurrently,
+/// so the order of the migrations vec isn't kept for them anymore
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParallelConfig {
+    /// How many migrations of a batch are executed at the same time, at least one
+    pub max_concurrency: usize,
+}
+
+impl Default for ParallelConfig {
+    fn default() -> Self {
+        Self { max_concurrency: 4 }
+    }
+}
+
+/// Expects migrations in the execution order, batches keep it.
+/// Without the config every migration is a batch of its own
+pub(crate) fn split_into_batches<'a>(
+    migrations: &[&'a dyn Migration],
+    parallel_config: Option<&ParallelConfig>,
+) -> Vec<Vec<&'a dyn Migration>> {
+    let mut batches: Vec<Vec<&dyn Migration>> = vec![];
+
+    for &migration in migrations {
+        match batches.last_mut() {
+            Some(batch)
+                if parallel_config.is_some()
+                    && batch[0].group() == migration.group()
+                    && batch
+                        .iter()
+                        .all(|&executed_with| !depend_on_each_other(executed_with, migration)) =>
+            {
+                batch.push(migration)
+            }
+            _ => batches.push(vec![migration]),
+        }
+    }
+
+    batches
+}
+
+/// A dependency is checked in both directions since down executes migrations in the reverse order
+fn depend_on_each_other(a: &dyn Migration, b: &dyn Migration) -> bool {
+    a.depends_on().iter().any(|id| id == b.get_id())
+        || b.depends_on().iter().any(|id| id == a.get_id())
+}
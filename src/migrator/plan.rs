@@ -0,0 +1,115 @@
+//! [`MigrationPlan`] describes which migrations an operation would execute
+//! and why, without executing anything
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    migration_record::MigrationRecord, migration_status::MigrationStatus,
+    operation_type::OperationType,
+};
+
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct MigrationPlan {
+    pub operation_type: OperationType,
+    /// All considered migrations in the order they would be executed
+    pub entries: Vec<MigrationPlanEntry>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct MigrationPlanEntry {
+    pub migration_id: String,
+    pub decision: PlanDecision,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub enum PlanDecision {
+    Run(RunReason),
+    Skip(SkipReason),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub enum RunReason {
+    /// There is no record about the migration
+    NeverRun,
+    /// The latest attempt has failed
+    PreviouslyFailed,
+    /// The latest attempt was cancelled by its timeout
+    PreviouslyTimedOut,
+    /// The migration is marked as in progress but nobody executes it
+    StaleInProgress,
+    /// The migrator which executed the migration has crashed
+    PreviouslyInterrupted,
+    /// The migration was rolled back, so it's pending again
+    RolledBack,
+    /// The migration was successfully applied, so it can be rolled back
+    Applied,
+    /// The latest rollback has failed, so the migration is still applied
+    PreviousRollbackFailed,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub enum SkipReason {
+    AlreadySucceeded,
+    /// The migration is being executed by someone else right now
+    InProgress,
+    /// The migration wasn't successfully applied, so there is nothing to roll back
+    NotApplied,
+    AlreadyRolledBack,
+    /// The status was written by a newer migrator, so it's unknown what to do with the migration
+    UnknownStatus,
+}
+
+impl MigrationPlan {
+    /// Ids of migrations which would be executed, in the execution order
+    pub fn ids_to_run(&self) -> Vec<String> {
+        self.entries
+            .iter()
+            .filter(|entry| matches!(entry.decision, PlanDecision::Run(_)))
+            .map(|entry| entry.migration_id.clone())
+            .collect()
+    }
+}
+
+/// Decides whether a migration with the passed record should be executed.
+/// `in_progress_is_stale` tells whether nobody executes the in progress migration right now
+pub(crate) fn decide(
+    record: Option<&MigrationRecord>,
+    operation_type: &OperationType,
+    in_progress_is_stale: bool,
+) -> PlanDecision {
+    let status = record.map(|record| &record.status);
+
+    match operation_type {
+        OperationType::Up => match status {
+            None => PlanDecision::Run(RunReason::NeverRun),
+            Some(MigrationStatus::Fail) => PlanDecision::Run(RunReason::PreviouslyFailed),
+            Some(MigrationStatus::TimedOut) => PlanDecision::Run(RunReason::PreviouslyTimedOut),
+            Some(MigrationStatus::RolledBack) => PlanDecision::Run(RunReason::RolledBack),
+            Some(MigrationStatus::Interrupted) => {
+                PlanDecision::Run(RunReason::PreviouslyInterrupted)
+            }
+            Some(MigrationStatus::Success) | Some(MigrationStatus::RollbackFailed) => {
+                PlanDecision::Skip(SkipReason::AlreadySucceeded)
+            }
+            Some(MigrationStatus::InProgress) if in_progress_is_stale => {
+                PlanDecision::Run(RunReason::StaleInProgress)
+            }
+            Some(MigrationStatus::InProgress) => PlanDecision::Skip(SkipReason::InProgress),
+            Some(MigrationStatus::Unknown) => PlanDecision::Skip(SkipReason::UnknownStatus),
+        },
+        // only successfully applied migrations are rolled back,
+        // it's unknown how much of a stale in progress or interrupted migration was applied
+        OperationType::Down => match status {
+            Some(MigrationStatus::Success) => PlanDecision::Run(RunReason::Applied),
+            Some(MigrationStatus::RollbackFailed) => {
+                PlanDecision::Run(RunReason::PreviousRollbackFailed)
+            }
+            Some(MigrationStatus::RolledBack) => PlanDecision::Skip(SkipReason::AlreadyRolledBack),
+            Some(MigrationStatus::InProgress) => PlanDecision::Skip(SkipReason::InProgress),
+            Some(MigrationStatus::Unknown) => PlanDecision::Skip(SkipReason::UnknownStatus),
+            None
+            | Some(MigrationStatus::Fail)
+            | Some(MigrationStatus::TimedOut)
+            | Some(MigrationStatus::Interrupted) => PlanDecision::Skip(SkipReason::NotApplied),
+        },
+    }
+}
@@ -0,0 +1,95 @@
+//! [`RunReport`] summarizes a single up/down call by its history,
+//! e.g. in order to see which migrations were executed concurrently
+use chrono::{DateTime, Utc};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    migration_history::MigrationHistoryRecord, migration_status::MigrationStatus,
+    operation_type::OperationType,
+};
+
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct RunReport {
+    pub run_id: String,
+    /// Migrations in the order they were started
+    pub entries: Vec<RunReportEntry>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct RunReportEntry {
+    pub migration_id: String,
+    pub direction: OperationType,
+    pub attempts: u32,
+    /// The start of the first attempt
+    pub start_date: Option<DateTime<Utc>>,
+    /// The end of the last attempt, absent while the migration is still executed
+    pub end_date: Option<DateTime<Utc>>,
+    /// The status of the last attempt
+    pub status: MigrationStatus,
+    /// Migrations of the run which were executed at the same time as this one
+    pub overlapped_with: Vec<String>,
+}
+
+impl RunReport {
+    /// Expects history records of the run in the order they were started
+    pub fn from_history(run_id: String, history: &[MigrationHistoryRecord]) -> Self {
+        let mut entries: Vec<RunReportEntry> = vec![];
+
+        for history_record in history {
+            match entries
+                .iter_mut()
+                .find(|entry| entry.migration_id == history_record.migration_id)
+            {
+                Some(entry) => {
+                    entry.attempts += 1;
+                    entry.end_date = history_record.end_date;
+                    entry.status = history_record.status.clone();
+                }
+                None => entries.push(RunReportEntry {
+                    migration_id: history_record.migration_id.clone(),
+                    direction: history_record.direction.clone(),
+                    attempts: 1,
+                    start_date: history_record.start_date,
+                    end_date: history_record.end_date,
+                    status: history_record.status.clone(),
+                    overlapped_with: vec![],
+                }),
+            }
+        }
+
+        let overlapped_with = entries
+            .iter()
+            .map(|entry| {
+                entries
+                    .iter()
+                    .filter(|other| other.migration_id != entry.migration_id)
+                    .filter(|other| overlap(entry, other))
+                    .map(|other| other.migration_id.clone())
+                    .collect::<Vec<String>>()
+            })
+            .collect::<Vec<Vec<String>>>();
+        for (entry, overlapped_with) in entries.iter_mut().zip(overlapped_with) {
+            entry.overlapped_with = overlapped_with;
+        }
+
+        Self { run_id, entries }
+    }
+
+    /// Whether at least two migrations of the run were executed at the same time
+    pub fn has_overlaps(&self) -> bool {
+        self.entries
+            .iter()
+            .any(|entry| !entry.overlapped_with.is_empty())
+    }
+}
+
+/// A migration without the end date is still executed
+fn overlap(a: &RunReportEntry, b: &RunReportEntry) -> bool {
+    match (a.start_date, b.start_date) {
+        (Some(a_start), Some(b_start)) => {
+            a.end_date.is_none_or(|a_end| b_start < a_end)
+                && b.end_date.is_none_or(|b_end| a_start < b_end)
+        }
+        _ => false,
+    }
+}
@@ -24,17 +24,7 @@ pub struct Shell {
 
 impl Shell {
     pub fn execute<S: AsRef<str> + std::fmt::Debug>(&self, db_name: S, query: S) -> Result<Value> {
-        let mongo = { Command::new("mongo").spawn() };
-        let mongo_sh = { Command::new("mongosh").spawn() };
-        let command = if mongo_sh.is_ok() {
-            "mongosh"
-        } else if mongo.is_ok() {
-            "mongo"
-        } else {
-            panic!("mongo[sh] is not installed");
-        };
-
-        let out = Command::new(command)
+        let out = Command::new(self.get_command())
             .arg("--host")
             .arg(&self.config.host)
             .arg("--port")
@@ -46,12 +36,51 @@ impl Shell {
             .spawn()?
             .wait_with_output();
 
-        let out = std::str::from_utf8(&out.expect("mongo shell finished").stdout)
+        Ok(self.out_to_value(&out.expect("mongo shell finished").stdout))
+    }
+
+    /// The same as [`Shell::execute`] but doesn't block an executor.  
+    /// The mongo shell process is killed once the returned future is dropped,
+    /// e.g. when a migration timeout is elapsed
+    pub async fn execute_async<S: AsRef<str> + std::fmt::Debug>(
+        &self,
+        db_name: S,
+        query: S,
+    ) -> Result<Value> {
+        let out = tokio::process::Command::new(self.get_command())
+            .arg("--host")
+            .arg(&self.config.host)
+            .arg("--port")
+            .arg(self.config.port.to_string())
+            .arg("--eval")
+            .arg(query.as_ref())
+            .arg(db_name.as_ref())
+            .stdout(std::process::Stdio::piped())
+            .kill_on_drop(true)
+            .output()
+            .await?;
+
+        Ok(self.out_to_value(&out.stdout))
+    }
+
+    fn get_command(&self) -> &'static str {
+        let mongo = { Command::new("mongo").spawn() };
+        let mongo_sh = { Command::new("mongosh").spawn() };
+        if mongo_sh.is_ok() {
+            "mongosh"
+        } else if mongo.is_ok() {
+            "mongo"
+        } else {
+            panic!("mongo[sh] is not installed");
+        }
+    }
+
+    fn out_to_value(&self, shell_out: &[u8]) -> Value {
+        let out = std::str::from_utf8(shell_out)
             .expect("u8 to string")
             .to_string();
-        let out_as_json = self.out_to_json(&out).unwrap_or(Value::String(out));
 
-        Ok(out_as_json)
+        self.out_to_json(&out).unwrap_or(Value::String(out))
     }
 
     fn out_to_json(&self, shell_out: &str) -> Result<Value> {
@@ -0,0 +1,96 @@
+//! An in progress record is prolonged by a heartbeat of its owner while the migration is running.
+//! A record which heartbeat has stopped belongs to a crashed migrator, such records are stale
+//! and handled according to [`StalePolicy`]
+use std::{sync::Arc, time::Duration};
+
+use chrono::Utc;
+use tokio::task::JoinHandle;
+
+use super::store::MigrationStore;
+use crate::{migration_record::MigrationRecord, migration_status::MigrationStatus};
+
+#[derive(Clone, Debug)]
+pub struct StaleConfig {
+    pub policy: StalePolicy,
+    /// How often an in progress record is prolonged, records of transactional migrations
+    /// aren't prolonged, so `stale_after` shouldn't be less than the server transaction lifetime
+    pub heartbeat_interval: Duration,
+    /// An in progress record is stale when it wasn't prolonged for this time
+    pub stale_after: Duration,
+}
+
+impl Default for StaleConfig {
+    fn default() -> Self {
+        Self {
+            policy: Default::default(),
+            heartbeat_interval: Duration::from_secs(10),
+            stale_after: Duration::from_secs(60),
+        }
+    }
+}
+
+/// What to do with stale in progress migrations when migrations are started
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum StalePolicy {
+    /// Don't execute anything until stale migrations are resolved,
+    /// e.g. with [`crate::migrator::with_migrations_vec::WithMigrationsVec::repair`]
+    Block,
+    /// Execute stale migrations again
+    #[default]
+    Rerun,
+    /// Save stale migrations as [`MigrationStatus::Interrupted`], up executes them again
+    MarkInterrupted,
+}
+
+/// Stops prolonging the record when the migration is finished
+pub(crate) struct HeartbeatGuard {
+    heartbeat: JoinHandle<()>,
+}
+
+impl Drop for HeartbeatGuard {
+    fn drop(&mut self) {
+        self.heartbeat.abort();
+    }
+}
+
+/// Prolongs the in progress record while it's still owned by the migrator
+pub(crate) fn start_heartbeat(
+    store: Arc<dyn MigrationStore>,
+    migration_record: &MigrationRecord,
+    heartbeat_interval: Duration,
+) -> HeartbeatGuard {
+    let migration_record = migration_record.clone();
+
+    let heartbeat = tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(heartbeat_interval).await;
+
+            match store.heartbeat(&migration_record, Utc::now()).await {
+                Ok(false) => break,
+                Ok(true) => {}
+                Err(error) => {
+                    tracing::warn!(
+                        message = "migration heartbeat failed",
+                        id = migration_record._id,
+                        error = error.to_string()
+                    );
+                }
+            }
+        }
+    });
+
+    HeartbeatGuard { heartbeat }
+}
+
+/// Records without a heartbeat are considered by their start date
+pub(crate) fn is_stale(migration_record: &MigrationRecord, stale_after: Duration) -> bool {
+    if migration_record.status != MigrationStatus::InProgress {
+        return false;
+    }
+
+    let stale_after = chrono::Duration::from_std(stale_after).unwrap_or(chrono::Duration::MAX);
+    migration_record
+        .heartbeat_at
+        .or(migration_record.start_date)
+        .is_none_or(|heartbeat_at| Utc::now() - heartbeat_at > stale_after)
+}
@@ -0,0 +1,246 @@
+//! Keeps the state in memory so that migrations can be executed without a database,
+//! e.g. in unit tests of migrations ordering and failures
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mongodb::ClientSession;
+
+use super::{HistoryFilter, MigrationStore, StoreError};
+use crate::{
+    migration_history::MigrationHistoryRecord, migration_record::MigrationRecord,
+    migration_status::MigrationStatus, state_metadata::StateMetadata,
+};
+
+/// Clones share the same state, so a clone kept by a test sees writes of the migrator
+#[derive(Clone, Debug, Default)]
+pub struct MemoryMigrationStore {
+    state: Arc<Mutex<MemoryState>>,
+}
+
+#[derive(Debug, Default)]
+struct MemoryState {
+    records: BTreeMap<String, MigrationRecord>,
+    history: Vec<MigrationHistoryRecord>,
+    metadata: Option<StateMetadata>,
+}
+
+impl MemoryMigrationStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Saves the record as is, e.g. in order to start from an already applied migration
+    pub fn insert_record(&self, migration_record: MigrationRecord) {
+        self.lock()
+            .records
+            .insert(migration_record._id.clone(), migration_record);
+    }
+
+    pub fn records(&self) -> BTreeMap<String, MigrationRecord> {
+        self.lock().records.clone()
+    }
+
+    pub fn history(&self) -> Vec<MigrationHistoryRecord> {
+        self.lock().history.clone()
+    }
+
+    pub fn metadata(&self) -> Option<StateMetadata> {
+        self.lock().metadata.clone()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MemoryState> {
+        // the state is never left half-written, so a panic of another holder doesn't matter
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Behaves like an upsert filtered by the version: a missing record is inserted
+    fn replace(&self, migration_record: &MigrationRecord, expected_version: i64) -> bool {
+        let mut state = self.lock();
+        if state
+            .records
+            .get(&migration_record._id)
+            .is_some_and(|stored| stored.version != expected_version)
+        {
+            return false;
+        }
+
+        state
+            .records
+            .insert(migration_record._id.clone(), migration_record.clone());
+        true
+    }
+}
+
+#[async_trait]
+impl MigrationStore for MemoryMigrationStore {
+    async fn load_records(
+        &self,
+        ids: &[String],
+    ) -> Result<BTreeMap<String, MigrationRecord>, StoreError> {
+        let state = self.lock();
+
+        Ok(ids
+            .iter()
+            .filter_map(|id| state.records.get(id))
+            .map(|migration_record| (migration_record._id.clone(), migration_record.clone()))
+            .collect())
+    }
+
+    async fn load_all_records(&self) -> Result<BTreeMap<String, MigrationRecord>, StoreError> {
+        Ok(self.records())
+    }
+
+    async fn load_record(&self, migration_id: &str) -> Result<Option<MigrationRecord>, StoreError> {
+        Ok(self.lock().records.get(migration_id).cloned())
+    }
+
+    async fn begin(
+        &self,
+        migration_record: &MigrationRecord,
+        expected_version: i64,
+    ) -> Result<bool, StoreError> {
+        Ok(self.replace(migration_record, expected_version))
+    }
+
+    /// There are no transactions in memory, so the session is ignored
+    async fn finish(
+        &self,
+        migration_record: &MigrationRecord,
+        expected_version: i64,
+        _session: Option<&mut ClientSession>,
+    ) -> Result<bool, StoreError> {
+        let mut state = self.lock();
+
+        Ok(match state.records.get_mut(&migration_record._id) {
+            Some(stored) if stored.version == expected_version => {
+                *stored = migration_record.clone();
+                true
+            }
+            _ => false,
+        })
+    }
+
+    async fn mark(
+        &self,
+        migration_record: &MigrationRecord,
+        expected_version: i64,
+    ) -> Result<bool, StoreError> {
+        Ok(self.replace(migration_record, expected_version))
+    }
+
+    async fn mark_not_executed(
+        &self,
+        migration_record: &MigrationRecord,
+    ) -> Result<(), StoreError> {
+        let mut state = self.lock();
+        let stored = state.records.get(&migration_record._id);
+
+        let migration_record = MigrationRecord {
+            attempts: stored.map_or(0, |stored| stored.attempts),
+            version: stored.map_or(0, |stored| stored.version) + 1,
+            ..migration_record.clone()
+        };
+        state
+            .records
+            .insert(migration_record._id.clone(), migration_record);
+
+        Ok(())
+    }
+
+    async fn forget(&self, migration_id: &str, expected_version: i64) -> Result<bool, StoreError> {
+        let mut state = self.lock();
+        if state
+            .records
+            .get(migration_id)
+            .is_none_or(|stored| stored.version != expected_version)
+        {
+            return Ok(false);
+        }
+
+        state.records.remove(migration_id);
+        Ok(true)
+    }
+
+    async fn heartbeat(
+        &self,
+        migration_record: &MigrationRecord,
+        heartbeat_at: DateTime<Utc>,
+    ) -> Result<bool, StoreError> {
+        let mut state = self.lock();
+
+        Ok(match state.records.get_mut(&migration_record._id) {
+            Some(stored)
+                if stored.owner == migration_record.owner
+                    && stored.version == migration_record.version
+                    && stored.status == MigrationStatus::InProgress =>
+            {
+                stored.heartbeat_at = Some(heartbeat_at);
+                true
+            }
+            _ => false,
+        })
+    }
+
+    async fn append_history(
+        &self,
+        history_record: &MigrationHistoryRecord,
+    ) -> Result<(), StoreError> {
+        let mut state = self.lock();
+
+        match state
+            .history
+            .iter_mut()
+            .find(|stored| stored._id == history_record._id)
+        {
+            Some(stored) => *stored = history_record.clone(),
+            None => state.history.push(history_record.clone()),
+        }
+
+        Ok(())
+    }
+
+    async fn load_history(
+        &self,
+        filter: HistoryFilter,
+    ) -> Result<Vec<MigrationHistoryRecord>, StoreError> {
+        let mut history = self
+            .history()
+            .into_iter()
+            .filter(|history_record| match &filter {
+                HistoryFilter::All => true,
+                HistoryFilter::MigrationId(migration_id) => {
+                    &history_record.migration_id == migration_id
+                }
+                HistoryFilter::RunId(run_id) => &history_record.run_id == run_id,
+            })
+            .collect::<Vec<MigrationHistoryRecord>>();
+        history.sort_by_key(|history_record| (history_record.start_date, history_record._id));
+
+        Ok(history)
+    }
+
+    async fn last_run_id(&self) -> Result<Option<String>, StoreError> {
+        Ok(self
+            .lock()
+            .history
+            .iter()
+            .max_by_key(|history_record| (history_record.start_date, history_record._id))
+            .map(|history_record| history_record.run_id.clone()))
+    }
+
+    async fn load_metadata(&self) -> Result<Option<StateMetadata>, StoreError> {
+        Ok(self.metadata())
+    }
+
+    async fn save_metadata(&self, metadata: &StateMetadata) -> Result<(), StoreError> {
+        self.lock().metadata = Some(metadata.clone());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,131 @@
+//! [`MigrationStore`] keeps the state of migrations: their latest records and the history of attempts.
+//! [`MongoMigrationStore`] is used by default, a custom store can keep the state elsewhere
+//! or wrap the default one, e.g. in order to audit every write.
+//! [`MemoryMigrationStore`] allows to execute migrations without a database
+use std::{collections::BTreeMap, sync::Arc};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mongodb::{error::Error as MongoDbError, ClientSession};
+use thiserror::Error;
+
+use crate::{
+    migration_history::MigrationHistoryRecord, migration_record::MigrationRecord,
+    state_metadata::StateMetadata,
+};
+
+mod memory;
+mod mongo;
+
+pub use self::{memory::MemoryMigrationStore, mongo::MongoMigrationStore};
+
+/// Writes of records are conditional: a record is written only if the stored one
+/// has the expected [`MigrationRecord::version`], 0 means there is no stored record
+/// or it was saved before versioning was introduced.
+/// Such writes return `false` when the stored record was modified by someone else
+#[async_trait]
+pub trait MigrationStore: Send + Sync {
+    /// Loads records of passed migrations, records without a stored state are absent
+    async fn load_records(
+        &self,
+        ids: &[String],
+    ) -> Result<BTreeMap<String, MigrationRecord>, StoreError>;
+
+    /// Loads all stored records, including ones of migrations which aren't passed anymore
+    async fn load_all_records(&self) -> Result<BTreeMap<String, MigrationRecord>, StoreError>;
+
+    async fn load_record(&self, migration_id: &str) -> Result<Option<MigrationRecord>, StoreError>;
+
+    /// Saves the in progress record of a started attempt, the record may not exist yet
+    async fn begin(
+        &self,
+        migration_record: &MigrationRecord,
+        expected_version: i64,
+    ) -> Result<bool, StoreError>;
+
+    /// Saves the record of a finished attempt over the in progress one.
+    /// The session is passed for transactional migrations, the write has to be a part of its transaction
+    async fn finish(
+        &self,
+        migration_record: &MigrationRecord,
+        expected_version: i64,
+        session: Option<&mut ClientSession>,
+    ) -> Result<bool, StoreError>;
+
+    /// Saves the record changed without executing the migration, the record may not exist yet
+    async fn mark(
+        &self,
+        migration_record: &MigrationRecord,
+        expected_version: i64,
+    ) -> Result<bool, StoreError>;
+
+    /// Saves a migration which wasn't started since a previous one has failed.
+    /// It's written regardless of the stored version, attempts of the stored record are kept
+    async fn mark_not_executed(&self, migration_record: &MigrationRecord)
+        -> Result<(), StoreError>;
+
+    /// Removes the record as if the migration was never executed
+    async fn forget(&self, migration_id: &str, expected_version: i64) -> Result<bool, StoreError>;
+
+    /// Prolongs the in progress record while it's owned by the record's owner,
+    /// returns `false` when the record isn't in progress or owned by someone else anymore
+    async fn heartbeat(
+        &self,
+        migration_record: &MigrationRecord,
+        heartbeat_at: DateTime<Utc>,
+    ) -> Result<bool, StoreError>;
+
+    async fn append_history(
+        &self,
+        history_record: &MigrationHistoryRecord,
+    ) -> Result<(), StoreError>;
+
+    /// Loads history records in the order they were started
+    async fn load_history(
+        &self,
+        filter: HistoryFilter,
+    ) -> Result<Vec<MigrationHistoryRecord>, StoreError>;
+
+    /// Returns the id of the latest run which made at least one attempt
+    async fn last_run_id(&self) -> Result<Option<String>, StoreError>;
+
+    /// Absent until records are written by a migrator which knows about the state format
+    async fn load_metadata(&self) -> Result<Option<StateMetadata>, StoreError>;
+
+    async fn save_metadata(&self, metadata: &StateMetadata) -> Result<(), StoreError>;
+}
+
+/// A store which isn't backed by MongoDB reports its own errors as [`StoreError::Other`]
+#[derive(Error, Debug, Clone)]
+pub enum StoreError {
+    #[error(transparent)]
+    MongoDb(#[from] MongoDbError),
+    #[error(transparent)]
+    Other(Arc<dyn std::error::Error + Send + Sync>),
+}
+
+impl StoreError {
+    pub fn other(error: impl std::error::Error + Send + Sync + 'static) -> Self {
+        StoreError::Other(Arc::new(error))
+    }
+
+    pub fn as_mongodb_error(&self) -> Option<&MongoDbError> {
+        match self {
+            StoreError::MongoDb(error) => Some(error),
+            StoreError::Other(_) => None,
+        }
+    }
+}
+
+impl From<bson::ser::Error> for StoreError {
+    fn from(error: bson::ser::Error) -> Self {
+        StoreError::MongoDb(error.into())
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum HistoryFilter {
+    All,
+    MigrationId(String),
+    RunId(String),
+}
@@ -0,0 +1,243 @@
+//! Keeps records in the migrations collection, the history in the history collection
+//! and [`StateMetadata`] in the metadata collection named after the migrations one
+use std::collections::BTreeMap;
+
+use async_trait::async_trait;
+use bson::Document;
+use chrono::{DateTime, Utc};
+use futures::TryStreamExt;
+use mongodb::{ClientSession, Collection, Database};
+
+use super::{HistoryFilter, MigrationStore, StoreError};
+use crate::{
+    migration_history::MigrationHistoryRecord,
+    migration_record::MigrationRecord,
+    migrator::lock,
+    state_metadata::{StateMetadata, STATE_METADATA_ID},
+};
+
+#[derive(Clone, Debug)]
+pub struct MongoMigrationStore {
+    records: Collection<MigrationRecord>,
+    history: Collection<MigrationHistoryRecord>,
+    metadata: Collection<StateMetadata>,
+}
+
+impl MongoMigrationStore {
+    pub fn new(db: &Database, collection_name: &str, history_collection_name: &str) -> Self {
+        Self {
+            records: db.collection(collection_name),
+            history: db.collection(history_collection_name),
+            metadata: db.collection(&format!("{collection_name}_metadata")),
+        }
+    }
+
+    async fn find_records(
+        &self,
+        filter: Document,
+    ) -> Result<BTreeMap<String, MigrationRecord>, StoreError> {
+        Ok(self
+            .records
+            .find(filter)
+            .await?
+            .map_ok(|migration_record| (migration_record._id.clone(), migration_record))
+            .try_collect()
+            .await?)
+    }
+
+    /// A duplicate key error means the record was inserted by someone else
+    async fn replace(
+        &self,
+        migration_record: &MigrationRecord,
+        expected_version: i64,
+    ) -> Result<bool, StoreError> {
+        match self
+            .records
+            .replace_one(
+                versioned_record_filter(&migration_record._id, expected_version),
+                migration_record,
+            )
+            .upsert(true)
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(error) if lock::is_duplicate_key_error(&error) => Ok(false),
+            Err(error) => Err(error.into()),
+        }
+    }
+}
+
+#[async_trait]
+impl MigrationStore for MongoMigrationStore {
+    async fn load_records(
+        &self,
+        ids: &[String],
+    ) -> Result<BTreeMap<String, MigrationRecord>, StoreError> {
+        self.find_records(bson::doc! {"_id": {"$in": ids}}).await
+    }
+
+    async fn load_all_records(&self) -> Result<BTreeMap<String, MigrationRecord>, StoreError> {
+        self.find_records(bson::doc! {}).await
+    }
+
+    async fn load_record(&self, migration_id: &str) -> Result<Option<MigrationRecord>, StoreError> {
+        Ok(self
+            .records
+            .find_one(bson::doc! {"_id": migration_id})
+            .await?)
+    }
+
+    async fn begin(
+        &self,
+        migration_record: &MigrationRecord,
+        expected_version: i64,
+    ) -> Result<bool, StoreError> {
+        self.replace(migration_record, expected_version).await
+    }
+
+    async fn finish(
+        &self,
+        migration_record: &MigrationRecord,
+        expected_version: i64,
+        session: Option<&mut ClientSession>,
+    ) -> Result<bool, StoreError> {
+        let update = self.records.update_one(
+            versioned_record_filter(&migration_record._id, expected_version),
+            bson::doc! {"$set": bson::to_document(migration_record)?},
+        );
+        let res = match session {
+            Some(session) => update.session(session).await?,
+            None => update.await?,
+        };
+
+        Ok(res.matched_count > 0)
+    }
+
+    async fn mark(
+        &self,
+        migration_record: &MigrationRecord,
+        expected_version: i64,
+    ) -> Result<bool, StoreError> {
+        self.replace(migration_record, expected_version).await
+    }
+
+    async fn mark_not_executed(
+        &self,
+        migration_record: &MigrationRecord,
+    ) -> Result<(), StoreError> {
+        let mut serialized_to_document_migration_record = bson::to_document(migration_record)?;
+        // the migration wasn't started, so only the version of an existing record is changed
+        serialized_to_document_migration_record.remove("attempts");
+        serialized_to_document_migration_record.remove("version");
+
+        self.records
+            .update_one(
+                bson::doc! {"_id": &migration_record._id},
+                bson::doc! {
+                    "$set": serialized_to_document_migration_record,
+                    "$inc": {"version": 1_i64},
+                },
+            )
+            .upsert(true)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn forget(&self, migration_id: &str, expected_version: i64) -> Result<bool, StoreError> {
+        let res = self
+            .records
+            .delete_one(versioned_record_filter(migration_id, expected_version))
+            .await?;
+
+        Ok(res.deleted_count > 0)
+    }
+
+    async fn heartbeat(
+        &self,
+        migration_record: &MigrationRecord,
+        heartbeat_at: DateTime<Utc>,
+    ) -> Result<bool, StoreError> {
+        // the version isn't changed, so the heartbeat doesn't fail version checks of the owner,
+        // it still conflicts with an open transaction which writes the record
+        let res = self
+            .records
+            .update_one(
+                bson::doc! {
+                    "_id": &migration_record._id,
+                    "owner": &migration_record.owner,
+                    "version": migration_record.version,
+                    "status": "InProgress",
+                },
+                bson::doc! {"$set": {"heartbeat_at": bson::to_bson(&heartbeat_at)?}},
+            )
+            .await?;
+
+        Ok(res.matched_count > 0)
+    }
+
+    async fn append_history(
+        &self,
+        history_record: &MigrationHistoryRecord,
+    ) -> Result<(), StoreError> {
+        self.history
+            .replace_one(bson::doc! {"_id": history_record._id}, history_record)
+            .upsert(true)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn load_history(
+        &self,
+        filter: HistoryFilter,
+    ) -> Result<Vec<MigrationHistoryRecord>, StoreError> {
+        let filter = match filter {
+            HistoryFilter::All => bson::doc! {},
+            HistoryFilter::MigrationId(migration_id) => bson::doc! {"migration_id": migration_id},
+            HistoryFilter::RunId(run_id) => bson::doc! {"run_id": run_id},
+        };
+
+        Ok(self
+            .history
+            .find(filter)
+            .sort(bson::doc! {"start_date": 1, "_id": 1})
+            .await?
+            .try_collect()
+            .await?)
+    }
+
+    async fn last_run_id(&self) -> Result<Option<String>, StoreError> {
+        Ok(self
+            .history
+            .find_one(bson::doc! {})
+            .sort(bson::doc! {"start_date": -1, "_id": -1})
+            .await?
+            .map(|history_record| history_record.run_id))
+    }
+
+    async fn load_metadata(&self) -> Result<Option<StateMetadata>, StoreError> {
+        Ok(self
+            .metadata
+            .find_one(bson::doc! {"_id": STATE_METADATA_ID})
+            .await?)
+    }
+
+    async fn save_metadata(&self, metadata: &StateMetadata) -> Result<(), StoreError> {
+        self.metadata
+            .replace_one(bson::doc! {"_id": &metadata._id}, metadata)
+            .upsert(true)
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// Records saved before versioning was introduced don't have the version field
+fn versioned_record_filter(migration_id: &str, expected_version: i64) -> Document {
+    if expected_version == 0 {
+        bson::doc! {"_id": migration_id, "version": {"$in": [0_i64, bson::Bson::Null]}}
+    } else {
+        bson::doc! {"_id": migration_id, "version": expected_version}
+    }
+}
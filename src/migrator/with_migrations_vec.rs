@@ -1,25 +1,76 @@
 use std::borrow::Cow;
-use std::{collections::BTreeMap, ops::Range, thread::sleep};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ops::Range,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use bson::{Bson, Document};
-use futures::StreamExt;
-use mongodb::results::InsertOneResult;
+use bson::oid::ObjectId;
+use chrono::Utc;
+use futures::{future::BoxFuture, stream, Future, StreamExt};
+use mongodb::{error::ErrorKind, ClientSession, Collection, Database};
+use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
 
 use super::{
-    shell::Shell, with_connection::WithConnection, with_retries::Retry,
-    with_shell_config::WithShellConfig, Env,
+    checksum::{ChecksumMismatch, ChecksumPolicy},
+    consistency::{ConsistencyConfig, ConsistencyPolicy, ConsistencyReport, OutOfOrderMigration},
+    dependencies,
+    env::{Extensions, Progress, ProgressHandler, ProgressReporter},
+    execution_strategy::ExecutionStrategy,
+    export::{ConflictPolicy, ImportReport, StateExport},
+    lock::{self, LockConfig, LockGuard, LockWait, MigrationLock},
+    observer::{MigrationObserver, MigrationRun},
+    parallel::{self, ParallelConfig},
+    plan::{self, MigrationPlan, MigrationPlanEntry},
+    report::RunReport,
+    shell::{Shell, ShellConfig},
+    stale::{self, StaleConfig, StalePolicy},
+    store::{HistoryFilter, MigrationStore, MongoMigrationStore},
+    with_retries::{self, RetryPolicy},
+    Env,
 };
 use crate::{
-    error::MigrationExecution, migration::Migration, migration_record::MigrationRecord,
+    error::MigrationExecution,
+    migration::Migration,
+    migration_history::MigrationHistoryRecord,
+    migration_record::MigrationRecord,
     migration_status::MigrationStatus,
+    operation_type::{ManualOperation, OperationType},
+    state_metadata::{StateMetadata, STATE_FORMAT_VERSION},
 };
 
 pub struct WithMigrationsVec {
-    pub with_shell_config: Option<WithShellConfig>,
-    pub with_connection: WithConnection,
+    /// The target database passed to migrations through [`Env::db`].
+    /// Absent when migrations are executed with [`super::store::MemoryMigrationStore`],
+    /// then migrations get no connection in their [`Env`] and the lock isn't used
+    pub db: Option<Database>,
+    /// Keeps records, the history and the lock instead of the target database,
+    /// e.g. a central database of migrators of several targets
+    pub state_db: Option<Database>,
     pub migrations: Vec<Box<dyn Migration>>,
-    pub with_retries_per_migration: Retry,
+    pub shell_config: Option<ShellConfig>,
+    pub retry_policy: RetryPolicy,
     pub collection_name: Option<String>,
+    pub history_collection_name: Option<String>,
+    pub lock_config: Option<LockConfig>,
+    pub execution_strategy: ExecutionStrategy,
+    pub checksum_policy: ChecksumPolicy,
+    pub default_timeout: Option<Duration>,
+    pub cancellation_token: CancellationToken,
+    pub progress_handler: Option<ProgressHandler>,
+    pub extensions: Extensions,
+    pub observers: Vec<Arc<dyn MigrationObserver>>,
+    /// Identifies the migrator in records of migrations it executes
+    pub owner: String,
+    pub stale_config: StaleConfig,
+    pub consistency_config: ConsistencyConfig,
+    /// Migrations are executed one by one when absent
+    pub parallel_config: Option<ParallelConfig>,
+    /// The state of migrations is kept in the migrations collections of the state database when absent,
+    /// without both of them migrator methods return [`MigrationExecution::StoreNotSet`]
+    pub store: Option<Arc<dyn MigrationStore>>,
 }
 
 impl WithMigrationsVec {
@@ -40,231 +91,1549 @@ impl WithMigrationsVec {
         }
     }
 
-    fn get_not_executed_migrations_ids(&self, first_failed_migration_index: usize) -> Vec<String> {
-        if self.migrations.len() - 1 == first_failed_migration_index {
-            vec![]
-        } else {
-            self.migrations[first_failed_migration_index + 1..]
-                .iter()
-                .map(|m| m.get_id().to_string())
-                .collect::<Vec<_>>()
+    /// Set where records, the history and the lock are kept, the target database by default.
+    /// The collection name isn't changed, so when several targets share the state database
+    /// each of them needs its own collection name, e.g. prefixed with the target database name
+    pub fn set_state_db(&mut self, state_db: Database) -> &mut WithMigrationsVec {
+        self.state_db = Some(state_db);
+        self
+    }
+
+    fn get_state_db(&self) -> Option<&Database> {
+        self.state_db.as_ref().or(self.db.as_ref())
+    }
+
+    /// Saved in records so that it's known which database a migration was applied to
+    fn get_target(&self) -> Option<String> {
+        self.db.as_ref().map(|db| db.name().to_string())
+    }
+
+    /// Set custom migrations history collection name
+    pub fn set_history_collection_name<S: Into<String>>(
+        &mut self,
+        history_collection_name: S,
+    ) -> &mut WithMigrationsVec {
+        self.history_collection_name = Some(history_collection_name.into());
+        self
+    }
+
+    /// Get history collection name, by default it's derived from the migrations collection name
+    fn get_history_collection_name(&self) -> Cow<'static, str> {
+        match self.history_collection_name.clone() {
+            None => format!("{}_history", self.get_collection_name()).into(),
+            Some(history_collection_name) => history_collection_name.into(),
         }
     }
 
-    async fn get_migrations_ids_to_execute_from_index(&self, range: Range<usize>) -> Vec<String> {
-        let ids = self.migrations[range]
+    /// Set where the state of migrations is kept, collection names aren't used by a custom store
+    pub fn set_store(&mut self, store: impl MigrationStore + 'static) -> &mut WithMigrationsVec {
+        self.store = Some(Arc::new(store));
+        self
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn get_store(&self) -> Result<Arc<dyn MigrationStore>, MigrationExecution> {
+        match (&self.store, self.get_state_db()) {
+            (Some(store), _) => Ok(store.clone()),
+            (None, Some(db)) => Ok(Arc::new(MongoMigrationStore::new(
+                db,
+                &self.get_collection_name(),
+                &self.get_history_collection_name(),
+            ))),
+            (None, None) => Err(MigrationExecution::StoreNotSet),
+        }
+    }
+
+    /// Set how failed migrations affect the next ones, [`ExecutionStrategy::FailFast`] by default
+    pub fn set_execution_strategy(
+        &mut self,
+        execution_strategy: ExecutionStrategy,
+    ) -> &mut WithMigrationsVec {
+        self.execution_strategy = execution_strategy;
+        self
+    }
+
+    /// Set how long a migration may be executed unless it has its own [`Migration::timeout`]
+    pub fn set_default_timeout(&mut self, default_timeout: Duration) -> &mut WithMigrationsVec {
+        self.default_timeout = Some(default_timeout);
+        self
+    }
+
+    /// Set the token which cancels [`Env::cancellation`] of running migrations
+    pub fn set_cancellation_token(
+        &mut self,
+        cancellation_token: CancellationToken,
+    ) -> &mut WithMigrationsVec {
+        self.cancellation_token = cancellation_token;
+        self
+    }
+
+    /// Set the handler which receives progress reported by migrations via [`Env::progress`]
+    pub fn set_progress_handler(
+        &mut self,
+        progress_handler: impl Fn(&Progress) + Send + Sync + 'static,
+    ) -> &mut WithMigrationsVec {
+        self.progress_handler = Some(Arc::new(progress_handler));
+        self
+    }
+
+    /// Add an observer which is notified about runs, observers are notified in the order they were added
+    pub fn add_observer(
+        &mut self,
+        observer: impl MigrationObserver + 'static,
+    ) -> &mut WithMigrationsVec {
+        self.observers.push(Arc::new(observer));
+        self
+    }
+
+    /// Set how stale in progress migrations are detected and handled,
+    /// [`super::stale::StalePolicy::Rerun`] by default
+    pub fn set_stale_config(&mut self, stale_config: StaleConfig) -> &mut WithMigrationsVec {
+        self.stale_config = stale_config;
+        self
+    }
+
+    /// Add a value which migrations get from [`Env::extensions`] by its type
+    pub fn set_extension<T: Send + Sync + 'static>(&mut self, value: T) -> &mut WithMigrationsVec {
+        self.extensions.insert(value);
+        self
+    }
+
+    /// Set how independent migrations are executed concurrently, see [`ParallelConfig`],
+    /// migrations are executed one by one by default
+    pub fn set_parallel_config(
+        &mut self,
+        parallel_config: ParallelConfig,
+    ) -> &mut WithMigrationsVec {
+        self.parallel_config = Some(parallel_config);
+        self
+    }
+
+    /// Set how failed migrations are retried unless a migration has its own [`Migration::retry_policy`]
+    pub fn set_retry_policy(&mut self, retry_policy: RetryPolicy) -> &mut WithMigrationsVec {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Set what to do when an already applied migration was changed, [`ChecksumPolicy::Warn`] by default
+    pub fn set_checksum_policy(
+        &mut self,
+        checksum_policy: ChecksumPolicy,
+    ) -> &mut WithMigrationsVec {
+        self.checksum_policy = checksum_policy;
+        self
+    }
+
+    /// Compares checksums of passed migrations with checksums saved when they were applied
+    async fn check_checksums(
+        &self,
+        migrations: &[&dyn Migration],
+    ) -> Result<(), MigrationExecution> {
+        if self.checksum_policy == ChecksumPolicy::Ignore {
+            return Ok(());
+        }
+
+        let ids = migrations
             .iter()
             .map(|migration| migration.get_id().to_string())
             .collect::<Vec<String>>();
+        let migration_records = self.load_migration_records(&ids).await?;
 
-        let mut failed = self.with_connection
-                .db
-                .clone()
-                .collection(&self.get_collection_name())
-                .find(
-                    bson::doc! {"_id": {"$in": ids.clone()}, "status": format!("{:?}", MigrationStatus::Fail)},
-                )
-		.await.unwrap().collect::<Vec<_>>().await
-		.into_iter()
-		// TODO(koc_kakoc): replace unwrap?
-		.map(|v| bson::from_bson(Bson::Document(v.unwrap())).unwrap())
-		.map(|v: MigrationRecord| v._id)
-		.collect::<Vec<String>>();
-
-        // TODO(koc_kakoc): use Set
-        let all = self
-            .with_connection
-            .db
-            .clone()
-            .collection(&self.get_collection_name())
-            .find(bson::doc! {})
+        let mismatches = migrations
+            .iter()
+            .filter_map(|migration| {
+                let migration_record = migration_records.get(migration.get_id())?;
+                if !migration_record.status.is_applied() {
+                    return None;
+                }
+
+                match (migration_record.checksum.clone(), migration.checksum()) {
+                    (Some(stored), Some(current)) if stored != current => Some(ChecksumMismatch {
+                        migration_id: migration.get_id().to_string(),
+                        stored,
+                        current,
+                    }),
+                    _ => None,
+                }
+            })
+            .collect::<Vec<ChecksumMismatch>>();
+
+        if mismatches.is_empty() {
+            return Ok(());
+        }
+
+        match self.checksum_policy {
+            ChecksumPolicy::Error => Err(MigrationExecution::ChecksumsMismatch { mismatches }),
+            _ => {
+                for mismatch in mismatches {
+                    tracing::warn!(
+                        message = "applied migration was changed",
+                        id = mismatch.migration_id,
+                        stored_checksum = mismatch.stored,
+                        current_checksum = mismatch.current
+                    );
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Set what to do when the migrations collection doesn't match passed migrations,
+    /// [`ConsistencyPolicy::Warn`] by default
+    pub fn set_consistency_config(
+        &mut self,
+        consistency_config: ConsistencyConfig,
+    ) -> &mut WithMigrationsVec {
+        self.consistency_config = consistency_config;
+        self
+    }
+
+    /// Reports records which don't match any passed migration
+    /// and never executed migrations placed before applied ones
+    pub async fn check_consistency(&self) -> Result<ConsistencyReport, MigrationExecution> {
+        let migrations = self.validate()?;
+
+        self.get_consistency_report(&migrations).await
+    }
+
+    async fn get_consistency_report(
+        &self,
+        migrations: &[&dyn Migration],
+    ) -> Result<ConsistencyReport, MigrationExecution> {
+        let mut migration_records = self.load_all_migration_records().await?;
+
+        let statuses = migrations
+            .iter()
+            .map(|migration| {
+                migration_records
+                    .remove(migration.get_id())
+                    .map(|migration_record| migration_record.status)
+            })
+            .collect::<Vec<Option<MigrationStatus>>>();
+
+        let out_of_order = migrations
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| statuses[*i].is_none())
+            .filter_map(|(i, migration)| {
+                let applied_after = migrations[i + 1..]
+                    .iter()
+                    .zip(&statuses[i + 1..])
+                    .filter(|(_, status)| status.as_ref().is_some_and(MigrationStatus::is_applied))
+                    .map(|(migration, _)| migration.get_id().to_string())
+                    .collect::<Vec<String>>();
+
+                (!applied_after.is_empty()).then(|| OutOfOrderMigration {
+                    migration_id: migration.get_id().to_string(),
+                    applied_after,
+                })
+            })
+            .collect();
+
+        Ok(ConsistencyReport {
+            // matched records were removed above
+            unknown_records: migration_records.into_keys().collect(),
+            out_of_order,
+        })
+    }
+
+    async fn check_consistency_policies(
+        &self,
+        migrations: &[&dyn Migration],
+    ) -> Result<(), MigrationExecution> {
+        let ConsistencyConfig {
+            unknown_records: unknown_records_policy,
+            out_of_order: out_of_order_policy,
+        } = &self.consistency_config;
+        if *unknown_records_policy == ConsistencyPolicy::Allow
+            && *out_of_order_policy == ConsistencyPolicy::Allow
+        {
+            return Ok(());
+        }
+
+        let report = self.get_consistency_report(migrations).await?;
+
+        if !report.unknown_records.is_empty() {
+            match unknown_records_policy {
+                ConsistencyPolicy::Allow => {}
+                ConsistencyPolicy::Warn => tracing::warn!(
+                    message = "migrations collection contains records of unknown migrations",
+                    ids = format!("{:?}", report.unknown_records)
+                ),
+                ConsistencyPolicy::Error => {
+                    return Err(MigrationExecution::UnknownMigrationRecords {
+                        migrations_ids: report.unknown_records,
+                    })
+                }
+            }
+        }
+
+        if !report.out_of_order.is_empty() {
+            match out_of_order_policy {
+                ConsistencyPolicy::Allow => {}
+                ConsistencyPolicy::Warn => {
+                    for out_of_order in &report.out_of_order {
+                        tracing::warn!(
+                            message = "never executed migration is placed before applied ones",
+                            id = out_of_order.migration_id,
+                            applied_after = format!("{:?}", out_of_order.applied_after)
+                        );
+                    }
+                }
+                ConsistencyPolicy::Error => {
+                    return Err(MigrationExecution::OutOfOrderMigrations {
+                        out_of_order: report.out_of_order,
+                    })
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Makes every up/down call acquire the migrations lock before executing migrations
+    /// so that concurrent migrators can't execute the same migrations simultaneously
+    pub fn set_lock_config(&mut self, lock_config: LockConfig) -> &mut WithMigrationsVec {
+        self.lock_config = Some(lock_config);
+        self
+    }
+
+    /// There is nobody to coordinate with without a connection, so there is no lock collection
+    fn get_lock_collection(&self) -> Option<Collection<MigrationLock>> {
+        self.get_state_db()
+            .map(|db| db.collection(&format!("{}_lock", self.get_collection_name())))
+    }
+
+    /// Returns the lock document if someone holds(or held and crashed) the migrations lock
+    pub async fn lock_status(&self) -> Result<Option<MigrationLock>, MigrationExecution> {
+        let Some(collection) = self.get_lock_collection() else {
+            return Ok(None);
+        };
+
+        lock::status(&collection)
             .await
-            .unwrap()
-            .collect::<Vec<_>>()
+            .map_err(|error| MigrationExecution::LockOperationFailed {
+                additional_info: error,
+            })
+    }
+
+    /// Removes the migrations lock regardless of its owner and returns the removed lock.  
+    /// Intended to be used by operators only when they are sure nobody executes migrations
+    pub async fn force_unlock(&self) -> Result<Option<MigrationLock>, MigrationExecution> {
+        let Some(collection) = self.get_lock_collection() else {
+            return Ok(None);
+        };
+        let removed_lock = lock::force_release(&collection).await.map_err(|error| {
+            MigrationExecution::LockOperationFailed {
+                additional_info: error,
+            }
+        })?;
+
+        tracing::warn!(
+            message = "migrations lock was forcibly removed",
+            lock = format!("{:?}", removed_lock)
+        );
+
+        Ok(removed_lock)
+    }
+
+    async fn acquire_lock(&self) -> Result<Option<LockGuard>, MigrationExecution> {
+        let (Some(lock_config), Some(collection)) =
+            (self.lock_config.clone(), self.get_lock_collection())
+        else {
+            return Ok(None);
+        };
+        let started_at = Instant::now();
+
+        loop {
+            let acquired = lock::try_acquire(&collection, &lock_config)
+                .await
+                .map_err(|error| MigrationExecution::LockOperationFailed {
+                    additional_info: error,
+                })?;
+
+            if acquired {
+                tracing::info!(
+                    message = "migrations lock acquired",
+                    owner = lock_config.owner
+                );
+                return Ok(Some(lock::start_heartbeat(collection, lock_config)));
+            }
+
+            match lock_config.wait {
+                LockWait::WaitWithTimeout {
+                    timeout,
+                    poll_interval,
+                } if started_at.elapsed() < timeout => {
+                    tokio::time::sleep(poll_interval).await;
+                }
+                _ => {
+                    return Err(MigrationExecution::LockNotAcquired {
+                        owner: lock_config.owner,
+                        held_by: self.lock_status().await?,
+                    })
+                }
+            }
+        }
+    }
+
+    async fn release_lock(&self, lock_guard: Option<LockGuard>) -> Result<(), MigrationExecution> {
+        let (Some(lock_guard), Some(lock_config), Some(collection)) =
+            (lock_guard, &self.lock_config, self.get_lock_collection())
+        else {
+            return Ok(());
+        };
+        drop(lock_guard);
+
+        lock::release(&collection, &lock_config.owner)
             .await
-            .into_iter()
-            // TODO(koc_kakoc): replace unwrap?
-            .map(|v| bson::from_bson(Bson::Document(v.unwrap())).unwrap())
-            .map(|v: MigrationRecord| v._id)
-            .collect::<Vec<String>>();
+            .map_err(|error| MigrationExecution::LockOperationFailed {
+                additional_info: error,
+            })?;
+
+        tracing::info!(
+            message = "migrations lock released",
+            owner = lock_config.owner
+        );
 
-        failed.extend(ids.into_iter().filter(|id| !all.contains(id)));
-        failed
+        Ok(())
     }
 
-    /// This function executes all passed migrations in the passed order
-    /// for migration in migrations
-    ///   createInProgressBson
-    ///   handleIfFailed
-    ///   saveInMongoAsInProgress
-    ///   handleIfResultWasntSaved
-    ///   up
-    ///   createFinishedBson
-    ///   handleIfFailed
-    ///   saveInMongoAsFinished
-    ///   handleIfResultWasntSaved
-    ///   returnIfMigrationUpWithFailedResultWithAllNextSavedAsFail
-    pub async fn up(&self) -> Result<(), MigrationExecution> {
-        self.exec(
-            Range {
-                start: 0,
-                end: self.migrations.len(),
-            },
-            OperationType::Up,
-        )
+    /// Returns all up/down attempts of the migration in the order they were started
+    pub async fn get_history_by_migration_id<S: AsRef<str>>(
+        &self,
+        migration_id: S,
+    ) -> Result<Vec<MigrationHistoryRecord>, MigrationExecution> {
+        self.find_history(HistoryFilter::MigrationId(
+            migration_id.as_ref().to_string(),
+        ))
         .await
     }
 
-    async fn exec(
+    /// Returns all attempts made during a single up/down call in the order they were started
+    pub async fn get_history_by_run_id<S: AsRef<str>>(
         &self,
-        range: Range<usize>,
-        operation_type: OperationType,
+        run_id: S,
+    ) -> Result<Vec<MigrationHistoryRecord>, MigrationExecution> {
+        self.find_history(HistoryFilter::RunId(run_id.as_ref().to_string()))
+            .await
+    }
+
+    /// Summarizes the run by its history, including which migrations were executed concurrently
+    pub async fn get_run_report<S: AsRef<str>>(
+        &self,
+        run_id: S,
+    ) -> Result<RunReport, MigrationExecution> {
+        let history = self.get_history_by_run_id(run_id.as_ref()).await?;
+
+        Ok(RunReport::from_history(
+            run_id.as_ref().to_string(),
+            &history,
+        ))
+    }
+
+    /// Returns the id of the latest run which made at least one attempt
+    pub async fn get_last_run_id(&self) -> Result<Option<String>, MigrationExecution> {
+        self.get_store()?.last_run_id().await.map_err(|error| {
+            MigrationExecution::HistoryNotLoaded {
+                additional_info: error,
+            }
+        })
+    }
+
+    async fn find_history(
+        &self,
+        filter: HistoryFilter,
+    ) -> Result<Vec<MigrationHistoryRecord>, MigrationExecution> {
+        self.get_store()?
+            .load_history(filter)
+            .await
+            .map_err(|error| MigrationExecution::HistoryNotLoaded {
+                additional_info: error,
+            })
+    }
+
+    async fn save_history_record(
+        &self,
+        history_record: &MigrationHistoryRecord,
     ) -> Result<(), MigrationExecution> {
-        self.validate()?;
+        self.get_store()?
+            .append_history(history_record)
+            .await
+            .map_err(|error| MigrationExecution::HistoryRecordNotSaved {
+                migration_id: history_record.migration_id.clone(),
+                additional_info: error,
+            })
+    }
 
-        let ids = self.get_migrations_ids_to_execute_from_index(range).await;
+    async fn load_migration_records(
+        &self,
+        ids: &[String],
+    ) -> Result<BTreeMap<String, MigrationRecord>, MigrationExecution> {
+        self.get_store()?.load_records(ids).await.map_err(|error| {
+            MigrationExecution::MigrationRecordsNotLoaded {
+                additional_info: error,
+            }
+        })
+    }
 
-        tracing::info!(
-            message = "the following migrations are going to be executed",
-            ids = format!("{:?}", ids),
-            op = format!("{:?}", operation_type.clone())
-        );
+    async fn load_all_migration_records(
+        &self,
+    ) -> Result<BTreeMap<String, MigrationRecord>, MigrationExecution> {
+        self.get_store()?.load_all_records().await.map_err(|error| {
+            MigrationExecution::MigrationRecordsNotLoaded {
+                additional_info: error,
+            }
+        })
+    }
 
-        let it = match operation_type {
-            OperationType::Up => self
-                .migrations
-                .iter()
-                .filter(|m| ids.contains(&m.get_id().to_string()))
-                .collect::<Vec<_>>(),
-            OperationType::Down => self
-                .migrations
-                .iter()
-                .rev()
-                .filter(|m| ids.contains(&m.get_id().to_string()))
-                .collect::<Vec<_>>(),
+    /// Upgrades records written in an older state format. It's executed under the lock
+    /// when the lock is configured, otherwise several migrators may try to upgrade the same records,
+    /// which is safe since a record is upgraded only while it still has the legacy version
+    async fn upgrade_state_format(&self) -> Result<(), MigrationExecution> {
+        let store = self.get_store()?;
+        let not_upgraded = |error| MigrationExecution::StateNotUpgraded {
+            additional_info: error,
+        };
+
+        let format_version = match store.load_metadata().await.map_err(not_upgraded)? {
+            Some(metadata) if metadata.format_version == STATE_FORMAT_VERSION => return Ok(()),
+            Some(metadata) if metadata.format_version > STATE_FORMAT_VERSION => {
+                return Err(MigrationExecution::StateFormatNotSupported {
+                    format_version: metadata.format_version,
+                    supported_format_version: STATE_FORMAT_VERSION,
+                })
+            }
+            Some(metadata) => metadata.format_version,
+            // records of 0.1.x are stored without the metadata, as well as a fresh state
+            None => 1,
         };
 
-        for (i, migration) in it.into_iter().enumerate() {
-            let mut retries = self.with_retries_per_migration.count;
+        let legacy_records = self
+            .load_all_migration_records()
+            .await?
+            .into_values()
+            .filter(MigrationRecord::is_legacy)
+            .collect::<Vec<MigrationRecord>>();
+
+        let mut upgraded = vec![];
+        for migration_record in &legacy_records {
+            let migration_record = migration_record.clone().upgraded(self.get_target());
+            // a record modified in the meantime is already written in the current format
+            if store
+                .mark(&migration_record, 0)
+                .await
+                .map_err(not_upgraded)?
+            {
+                upgraded.push(migration_record._id);
+            }
+        }
+
+        store
+            .save_metadata(&StateMetadata::current(self.owner.clone()))
+            .await
+            .map_err(not_upgraded)?;
+
+        if !legacy_records.is_empty() {
+            tracing::warn!(
+                message = "migrations state was upgraded",
+                from = format_version,
+                to = STATE_FORMAT_VERSION,
+                ids = format!("{:?}", upgraded)
+            );
+        }
+
+        Ok(())
+    }
+
+    async fn get_migrations_plan(
+        &self,
+        migrations: &[&dyn Migration],
+        operation_type: OperationType,
+        all_in_progress_are_stale: bool,
+    ) -> Result<MigrationPlan, MigrationExecution> {
+        let mut ids = migrations
+            .iter()
+            .map(|migration| migration.get_id().to_string())
+            .collect::<Vec<String>>();
+        if operation_type == OperationType::Down {
+            ids.reverse();
+        }
+
+        let migration_records = self.load_migration_records(&ids).await?;
+
+        Ok(MigrationPlan {
+            entries: ids
+                .into_iter()
+                .map(|migration_id| {
+                    let migration_record = migration_records.get(&migration_id);
+                    let in_progress_is_stale = all_in_progress_are_stale
+                        || migration_record.is_some_and(|migration_record| {
+                            stale::is_stale(migration_record, self.stale_config.stale_after)
+                        });
+
+                    MigrationPlanEntry {
+                        decision: plan::decide(
+                            migration_record,
+                            &operation_type,
+                            in_progress_is_stale,
+                        ),
+                        migration_id,
+                    }
+                })
+                .collect(),
+            operation_type,
+        })
+    }
+
+    /// Returns which migrations [`WithMigrationsVec::up`] or [`WithMigrationsVec::down`]
+    /// would execute and why, without executing anything
+    pub async fn plan(
+        &self,
+        operation_type: OperationType,
+    ) -> Result<MigrationPlan, MigrationExecution> {
+        let migrations = self.validate()?;
+        let all_in_progress_are_stale = self.all_in_progress_are_stale().await?;
+
+        self.get_migrations_plan(&migrations, operation_type, all_in_progress_are_stale)
+            .await
+    }
+
+    /// All in progress migrations are stale when nobody is able to execute migrations right now,
+    /// otherwise only ones which heartbeat has stopped
+    async fn all_in_progress_are_stale(&self) -> Result<bool, MigrationExecution> {
+        Ok(match &self.lock_config {
+            None => false,
+            Some(lock_config) => self.lock_status().await?.is_none_or(|migration_lock| {
+                migration_lock.is_expired() || migration_lock.owner == lock_config.owner
+            }),
+        })
+    }
+
+    async fn find_stale_migration_records(
+        &self,
+        migrations: &[&dyn Migration],
+        all_in_progress_are_stale: bool,
+    ) -> Result<Vec<MigrationRecord>, MigrationExecution> {
+        let ids = migrations
+            .iter()
+            .map(|migration| migration.get_id().to_string())
+            .collect::<Vec<String>>();
+
+        Ok(self
+            .load_migration_records(&ids)
+            .await?
+            .into_values()
+            .filter(|migration_record| {
+                migration_record.status == MigrationStatus::InProgress
+                    && (all_in_progress_are_stale
+                        || stale::is_stale(migration_record, self.stale_config.stale_after))
+            })
+            .collect())
+    }
+
+    /// Returns ids of migrations which were saved as interrupted,
+    /// a record modified by someone else in the meantime is left as is
+    async fn mark_interrupted(
+        &self,
+        migration_records: Vec<MigrationRecord>,
+    ) -> Result<Vec<String>, MigrationExecution> {
+        let mut interrupted = vec![];
+
+        for migration_record in migration_records {
+            let expected_version = migration_record.version;
+            let migration_record = migration_record.migration_interrupted().next_version();
+
+            let saved = self
+                .get_store()?
+                .finish(&migration_record, expected_version, None)
+                .await
+                .map_err(
+                    |error| MigrationExecution::FinishedButNotSavedDueMongoError {
+                        migration_id: migration_record._id.clone(),
+                        migration_status: format!("{:?}", &migration_record.status),
+                        additional_info: error,
+                        next_not_executed_migrations_ids: vec![],
+                    },
+                )?;
+
+            if saved {
+                tracing::warn!(
+                    message = "stale migration was saved as interrupted",
+                    id = migration_record._id
+                );
+                interrupted.push(migration_record._id);
+            }
+        }
+
+        Ok(interrupted)
+    }
+
+    /// Applies [`StaleConfig::policy`] to stale in progress migrations before executing anything
+    async fn handle_stale_migrations(
+        &self,
+        migrations: &[&dyn Migration],
+        all_in_progress_are_stale: bool,
+    ) -> Result<(), MigrationExecution> {
+        let stale_migration_records = self
+            .find_stale_migration_records(migrations, all_in_progress_are_stale)
+            .await?;
+        if stale_migration_records.is_empty() {
+            return Ok(());
+        }
+
+        match self.stale_config.policy {
+            StalePolicy::Rerun => Ok(()),
+            StalePolicy::Block => Err(MigrationExecution::StaleMigrationsFound {
+                migrations_ids: stale_migration_records
+                    .into_iter()
+                    .map(|migration_record| migration_record._id)
+                    .collect(),
+            }),
+            StalePolicy::MarkInterrupted => self
+                .mark_interrupted(stale_migration_records)
+                .await
+                .map(|_| ()),
+        }
+    }
+
+    /// Saves stale in progress migrations as interrupted so that the next up executes them again,
+    /// returns ids of repaired migrations
+    pub async fn repair(&self) -> Result<Vec<String>, MigrationExecution> {
+        let migrations = self.validate()?;
+        let all_in_progress_are_stale = self.all_in_progress_are_stale().await?;
+        let stale_migration_records = self
+            .find_stale_migration_records(&migrations, all_in_progress_are_stale)
+            .await?;
+
+        self.mark_interrupted(stale_migration_records).await
+    }
+
+    /// This function executes all passed migrations in the passed order
+    /// for migration in migrations
+    ///   createInProgressBson
+    ///   handleIfFailed
+    ///   saveInMongoAsInProgress
+    ///   handleIfResultWasntSaved
+    ///   up
+    ///   createFinishedBson
+    ///   handleIfFailed
+    ///   saveInMongoAsFinished
+    ///   handleIfResultWasntSaved
+    ///   returnIfMigrationUpWithFailedResultWithAllNextSavedAsFail
+    pub async fn up(&self) -> Result<(), MigrationExecution> {
+        self.exec(
+            Range {
+                start: 0,
+                end: self.migrations.len(),
+            },
+            OperationType::Up,
+            None,
+        )
+        .await
+    }
+
+    /// Executes migrations from the range which have to be executed,
+    /// the range is applied to migrations ordered by their dependencies,
+    /// `limit` restricts how many of them will be executed
+    async fn exec(
+        &self,
+        range: Range<usize>,
+        operation_type: OperationType,
+        limit: Option<usize>,
+    ) -> Result<(), MigrationExecution> {
+        let mut run = None;
+        let res = self
+            .exec_locked(range, operation_type.clone(), limit, &mut run)
+            .await;
+
+        if !self.observers.is_empty() {
+            self.notify_run_finished(run, operation_type, &res).await;
+        }
+
+        res
+    }
+
+    /// Sets the run once it's known which migrations are going to be executed
+    async fn exec_locked(
+        &self,
+        range: Range<usize>,
+        operation_type: OperationType,
+        limit: Option<usize>,
+        run: &mut Option<MigrationRun>,
+    ) -> Result<(), MigrationExecution> {
+        let migrations = self.validate()?;
+
+        let lock_guard = self.acquire_lock().await?;
+        let res = async {
+            self.upgrade_state_format().await?;
+            self.check_checksums(&migrations).await?;
+            self.check_consistency_policies(&migrations).await?;
+            self.handle_stale_migrations(&migrations[range.clone()], lock_guard.is_some())
+                .await?;
+            self.exec_migrations(
+                &migrations[range],
+                operation_type,
+                limit,
+                lock_guard.is_some(),
+                run,
+            )
+            .await
+        }
+        .await;
+        let released = self.release_lock(lock_guard).await;
+
+        res.and(released)
+    }
+
+    /// Observers are notified about errors which have stopped the run before it was started too,
+    /// such a run has no migrations
+    async fn notify_run_finished(
+        &self,
+        run: Option<MigrationRun>,
+        operation_type: OperationType,
+        res: &Result<(), MigrationExecution>,
+    ) {
+        let run = match run {
+            Some(run) => run,
+            None => {
+                let run = MigrationRun {
+                    run_id: ObjectId::new().to_hex(),
+                    operation_type,
+                    migrations_ids: vec![],
+                };
+                if let Err(e) = res {
+                    for observer in &self.observers {
+                        observer.on_failure(&run, e).await;
+                    }
+                }
+                run
+            }
+        };
+
+        for observer in &self.observers {
+            observer.after_all(&run, res).await;
+        }
+    }
+
+    /// Notifies observers about the error which has stopped the run before `exec` was called
+    async fn stop_before_run(
+        &self,
+        operation_type: OperationType,
+        e: MigrationExecution,
+    ) -> Result<(), MigrationExecution> {
+        let res = Err(e);
+        self.notify_run_finished(None, operation_type, &res).await;
+
+        res
+    }
+
+    async fn exec_migrations(
+        &self,
+        migrations: &[&dyn Migration],
+        operation_type: OperationType,
+        limit: Option<usize>,
+        all_in_progress_are_stale: bool,
+        started_run: &mut Option<MigrationRun>,
+    ) -> Result<(), MigrationExecution> {
+        let run_id = ObjectId::new().to_hex();
+        let mut ids = self
+            .get_migrations_plan(
+                migrations,
+                operation_type.clone(),
+                all_in_progress_are_stale,
+            )
+            .await?
+            .ids_to_run();
+        if let Some(limit) = limit {
+            ids.truncate(limit);
+        }
+
+        tracing::info!(
+            message = "the following migrations are going to be executed",
+            ids = format!("{:?}", ids),
+            op = format!("{:?}", operation_type.clone()),
+            run_id = run_id
+        );
+
+        let migrations = ids
+            .iter()
+            .filter_map(|id| migrations.iter().find(|m| m.get_id() == id))
+            .copied()
+            .collect::<Vec<&dyn Migration>>();
+
+        let run = MigrationRun {
+            run_id,
+            operation_type,
+            migrations_ids: ids,
+        };
+        for observer in &self.observers {
+            observer.before_all(&run).await;
+        }
+
+        let res = self.run_migrations(&migrations, &run).await;
+        *started_run = Some(run);
+
+        res
+    }
+
+    /// Runs already planned migrations according to the execution strategy,
+    /// migrations of a batch are executed concurrently and the next batch waits for all of them
+    async fn run_migrations(
+        &self,
+        migrations: &[&dyn Migration],
+        run: &MigrationRun,
+    ) -> Result<(), MigrationExecution> {
+        let max_concurrency = self
+            .parallel_config
+            .as_ref()
+            .map_or(1, |parallel_config| parallel_config.max_concurrency.max(1));
+        let mut failures = vec![];
+        let mut failed = BTreeSet::new();
+        let mut started = 0;
+
+        for batch in parallel::split_into_batches(migrations, self.parallel_config.as_ref()) {
+            started += batch.len();
+            // with TryAll migrations blocked by failed ones aren't executed,
+            // batches keep the dependencies order, so blocked ones are found transitively
+            let (batch, blocked) = batch.into_iter().partition::<Vec<_>, _>(|migration| {
+                blocked_by(*migration, migrations, &run.operation_type, &failed).is_empty()
+            });
+            for migration in blocked {
+                let blocked_by = blocked_by(migration, migrations, &run.operation_type, &failed);
+                failed.insert(migration.get_id().to_string());
+                failures.push((
+                    migration.get_id().to_string(),
+                    self.skip_blocked_migration(migration, blocked_by, run)
+                        .await,
+                ));
+            }
+
+            // with TryAll a failed migration doesn't prevent the next ones from execution,
+            // with FailFast migrations of the same batch are finished anyway
+            let next_not_executed_migrations_ids = match self.execution_strategy {
+                ExecutionStrategy::FailFast => &run.migrations_ids[started..],
+                ExecutionStrategy::TryAll => &[],
+            };
+
+            let runs = batch
+                .into_iter()
+                .map(|migration| -> BoxFuture<'_, _> {
+                    Box::pin(async move {
+                        let res = self
+                            .run_migration(migration, next_not_executed_migrations_ids, run)
+                            .await;
+                        (migration, res)
+                    })
+                })
+                .collect::<Vec<_>>();
+            // boxed futures are Send for any lifetime, unlike futures of the closure
+            let results = stream::iter(runs)
+                .buffered(max_concurrency)
+                .collect::<Vec<_>>()
+                .await;
+
+            let mut first_failure = None;
+            for (migration, res) in results {
+                if let Err(e) = res {
+                    match self.execution_strategy {
+                        ExecutionStrategy::FailFast => {
+                            first_failure.get_or_insert(e);
+                        }
+                        ExecutionStrategy::TryAll => {
+                            failed.insert(migration.get_id().to_string());
+                            failures.push((migration.get_id().to_string(), e));
+                        }
+                    }
+                }
+            }
+            if let Some(e) = first_failure {
+                return Err(e);
+            }
+        }
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(MigrationExecution::TryAllFinishedWithFailures { failures })
+        }
+    }
+
+    /// Runs the migration with retries and notifies observers about it
+    async fn run_migration(
+        &self,
+        migration: &dyn Migration,
+        next_not_executed_migrations_ids: &[String],
+        run: &MigrationRun,
+    ) -> Result<(), MigrationExecution> {
+        for observer in &self.observers {
+            observer.before_each(run, migration.get_id()).await;
+        }
+
+        let res = self
+            .run_with_retries(migration, next_not_executed_migrations_ids, run)
+            .await;
+
+        if !self.observers.is_empty() {
+            self.notify_migration_finished(migration, next_not_executed_migrations_ids, run, &res)
+                .await;
+        }
+
+        res.map(|_| ())
+    }
+
+    /// Retries the migration according to its retry policy, returns the record saved by the last attempt
+    async fn run_with_retries(
+        &self,
+        migration: &dyn Migration,
+        next_not_executed_migrations_ids: &[String],
+        run: &MigrationRun,
+    ) -> Result<MigrationRecord, MigrationExecution> {
+        let retry_policy = migration
+            .retry_policy()
+            .unwrap_or_else(|| self.retry_policy.clone());
+        let started_at = Instant::now();
+        let mut attempt = 1;
+
+        loop {
+            let res = self
+                .try_run_migration(
+                    migration,
+                    next_not_executed_migrations_ids,
+                    run.operation_type.clone(),
+                    &run.run_id,
+                    attempt,
+                    &retry_policy,
+                )
+                .await;
+            let AttemptFailure {
+                error: e,
+                retryable,
+            } = match res {
+                Ok(migration_record) => return Ok(migration_record),
+                Err(failure) => failure,
+            };
+
+            self.trace_result(migration, &Err(e.clone()), run.operation_type.clone());
+            if !retryable {
+                return Err(e);
+            }
+            let Some(delay) = retry_policy.next_delay(attempt, started_at.elapsed(), &e) else {
+                return Err(e);
+            };
+            attempt += 1;
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Saves the migration blocked by failed ones as not executed and notifies observers about it,
+    /// a blocked rollback leaves the migration applied, so its record is kept as is
+    async fn skip_blocked_migration(
+        &self,
+        migration: &dyn Migration,
+        blocked_by: Vec<String>,
+        run: &MigrationRun,
+    ) -> MigrationExecution {
+        for observer in &self.observers {
+            observer.before_each(run, migration.get_id()).await;
+        }
+
+        let mut error = MigrationExecution::DependencyFailed {
+            migration_id: migration.get_id().to_string(),
+            failed: blocked_by,
+        };
+        if run.operation_type == OperationType::Up {
+            if let Err(e) = self
+                .save_not_executed_migrations(&[migration.get_id().to_string()])
+                .await
+            {
+                error = e;
+            }
+        }
+
+        if !self.observers.is_empty() {
+            self.notify_migration_finished(migration, &[], run, &Err(error.clone()))
+                .await;
+        }
+
+        error
+    }
+
+    async fn notify_migration_finished(
+        &self,
+        migration: &dyn Migration,
+        next_not_executed_migrations_ids: &[String],
+        run: &MigrationRun,
+        res: &Result<MigrationRecord, MigrationExecution>,
+    ) {
+        // a failed migration doesn't return its record, it might be not saved at all
+        let record = match res {
+            Ok(record) => Some(record.clone()),
+            Err(_) => self
+                .load_migration_record(migration, next_not_executed_migrations_ids)
+                .await
+                .ok()
+                .flatten(),
+        };
+
+        for observer in &self.observers {
+            if let Some(record) = &record {
+                observer.after_each(run, record).await;
+            }
+            if let Err(e) = res {
+                observer.on_failure(run, e).await;
+            }
+        }
+    }
+
+    /// Rollbacks all successfully applied migrations in the reverse order
+    pub async fn down(&self) -> Result<(), MigrationExecution> {
+        self.exec(
+            Range {
+                start: 0,
+                end: self.migrations.len(),
+            },
+            OperationType::Down,
+            None,
+        )
+        .await
+    }
+
+    async fn save_not_executed_migrations(
+        &self,
+        not_executed_migrations_ids: &[String],
+    ) -> Result<(), MigrationExecution> {
+        for (i, migration_id) in not_executed_migrations_ids.iter().enumerate() {
+            let migration_record = MigrationRecord::migration_start(migration_id.to_string())
+                .applied_to(self.get_target())
+                .migration_failed();
+
+            self.get_store()?
+                .mark_not_executed(&migration_record)
+                .await
+                .map_err(
+                    |error| MigrationExecution::FinishedButNotSavedDueMongoError {
+                        migration_id: migration_id.to_string(),
+                        migration_status: format!("{:?}", &migration_record.status),
+                        additional_info: error,
+                        next_not_executed_migrations_ids: not_executed_migrations_ids[i + 1..]
+                            .to_vec(),
+                    },
+                )?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the migration position among migrations ordered by their dependencies
+    #[allow(clippy::result_large_err)]
+    fn get_migration_index(&self, migration_id: &str) -> Result<usize, MigrationExecution> {
+        self.validate()?
+            .iter()
+            .position(|migration| migration.get_id() == migration_id)
+            .ok_or_else(|| MigrationExecution::MigrationFromVecNotFound {
+                migration_id: migration_id.to_string(),
+            })
+    }
+
+    /// Ups all migrations from the passed before vec up to and including the passed one
+    pub async fn up_to(&self, migration_id: String) -> Result<(), MigrationExecution> {
+        let i = match self.get_migration_index(&migration_id) {
+            Ok(i) => i,
+            Err(e) => return self.stop_before_run(OperationType::Up, e).await,
+        };
+
+        self.exec(
+            Range {
+                start: 0,
+                end: i + 1,
+            },
+            OperationType::Up,
+            None,
+        )
+        .await
+    }
+
+    /// Rollbacks all migrations placed after the passed one in the reverse order,
+    /// the passed migration itself stays untouched
+    pub async fn down_to(&self, migration_id: String) -> Result<(), MigrationExecution> {
+        let i = match self.get_migration_index(&migration_id) {
+            Ok(i) => i,
+            Err(e) => return self.stop_before_run(OperationType::Down, e).await,
+        };
+
+        self.exec(
+            Range {
+                start: i + 1,
+                end: self.migrations.len(),
+            },
+            OperationType::Down,
+            None,
+        )
+        .await
+    }
+
+    /// Rollbacks the last `count` migrations in the reverse order
+    pub async fn down_last(&self, count: usize) -> Result<(), MigrationExecution> {
+        self.exec(
+            Range {
+                start: 0,
+                end: self.migrations.len(),
+            },
+            OperationType::Down,
+            Some(count),
+        )
+        .await
+    }
+
+    /// Tries to up a migration from the passed before vec
+    pub async fn up_single_from_vec(&self, migration_id: String) -> Result<(), MigrationExecution> {
+        let i = match self.get_migration_index(&migration_id) {
+            Ok(i) => i,
+            Err(e) => return self.stop_before_run(OperationType::Up, e).await,
+        };
+
+        self.exec(
+            Range {
+                start: i,
+                end: i + 1,
+            },
+            OperationType::Up,
+            None,
+        )
+        .await
+    }
+
+    /// Tries do rollback a migration from the bassed before vec
+    pub async fn down_single_from_vec(
+        &self,
+        migration_id: String,
+    ) -> Result<(), MigrationExecution> {
+        let i = match self.get_migration_index(&migration_id) {
+            Ok(i) => i,
+            Err(e) => return self.stop_before_run(OperationType::Down, e).await,
+        };
+
+        self.exec(
+            Range {
+                start: i,
+                end: i + 1,
+            },
+            OperationType::Down,
+            None,
+        )
+        .await
+    }
+
+    /// Marks all migrations up to and including the passed one as applied without executing them,
+    /// e.g. when the migrator is adopted for a database which already has them applied.
+    /// Returns ids of marked migrations, already applied ones are left as is
+    pub async fn baseline_up_to(
+        &self,
+        migration_id: String,
+    ) -> Result<Vec<String>, MigrationExecution> {
+        let migrations = self.validate()?;
+        let i = self.get_migration_index(&migration_id)?;
+        let ids = migrations[..=i]
+            .iter()
+            .map(|migration| migration.get_id().to_string())
+            .collect::<Vec<String>>();
+        let migration_records = self.load_migration_records(&ids).await?;
+
+        self.apply_manual_changes(
+            ids.into_iter()
+                .filter(|id| {
+                    migration_records
+                        .get(id)
+                        .is_none_or(|migration_record| !migration_record.status.is_applied())
+                })
+                .map(|id| (id, ManualOperation::Baseline))
+                .collect(),
+        )
+        .await
+    }
+
+    /// Saves the migration as successfully applied without executing it
+    pub async fn mark_applied(&self, migration_id: String) -> Result<(), MigrationExecution> {
+        self.get_migration_index(&migration_id)?;
+
+        self.apply_manual_changes(vec![(migration_id, ManualOperation::MarkApplied)])
+            .await
+            .map(|_| ())
+    }
+
+    /// Saves the migration as failed without executing it, so the next up executes it
+    pub async fn mark_failed(&self, migration_id: String) -> Result<(), MigrationExecution> {
+        self.get_migration_index(&migration_id)?;
+
+        self.apply_manual_changes(vec![(migration_id, ManualOperation::MarkFailed)])
+            .await
+            .map(|_| ())
+    }
+
+    /// Removes the migration record as if the migration was never executed,
+    /// the migration doesn't have to be among passed migrations, e.g. it was deleted from the code
+    pub async fn forget(&self, migration_id: String) -> Result<(), MigrationExecution> {
+        self.apply_manual_changes(vec![(migration_id, ManualOperation::Forget)])
+            .await
+            .map(|_| ())
+    }
+
+    /// Returns all migration records with their history, including records of migrations
+    /// which aren't passed to this migrator
+    pub async fn export_state(&self) -> Result<StateExport, MigrationExecution> {
+        let metadata = self.get_store()?.load_metadata().await.map_err(|error| {
+            MigrationExecution::MigrationRecordsNotLoaded {
+                additional_info: error,
+            }
+        })?;
+        let records = self.load_all_migration_records().await?;
+        let history = self.find_history(HistoryFilter::All).await?;
+
+        Ok(StateExport::new(
+            metadata,
+            records.into_values().collect(),
+            history,
+        ))
+    }
+
+    /// Writes exported records and their history into the migrations collection under the lock,
+    /// `conflict_policy` decides what to do with records of migrations which have a record already
+    pub async fn import_state(
+        &self,
+        state_export: StateExport,
+        conflict_policy: ConflictPolicy,
+    ) -> Result<ImportReport, MigrationExecution> {
+        if state_export.format_version > STATE_FORMAT_VERSION {
+            return Err(MigrationExecution::StateFormatNotSupported {
+                format_version: state_export.format_version,
+                supported_format_version: STATE_FORMAT_VERSION,
+            });
+        }
+
+        let lock_guard = self.acquire_lock().await?;
+        let res = async {
+            self.upgrade_state_format().await?;
+            let existing_records = self.load_all_migration_records().await?;
+
+            if conflict_policy == ConflictPolicy::Fail {
+                let conflicts = state_export
+                    .records
+                    .iter()
+                    .filter(|migration_record| existing_records.contains_key(&migration_record._id))
+                    .map(|migration_record| migration_record._id.clone())
+                    .collect::<Vec<String>>();
+                if !conflicts.is_empty() {
+                    return Err(MigrationExecution::ImportConflicts {
+                        migrations_ids: conflicts,
+                    });
+                }
+            }
+
+            let mut import_report = ImportReport::default();
+            for migration_record in state_export.records {
+                let migration_id = migration_record._id.clone();
+                let existing_record = existing_records.get(&migration_id);
+                if existing_record.is_some() && conflict_policy == ConflictPolicy::Skip {
+                    import_report.skipped.push(migration_id);
+                    continue;
+                }
 
-            while let Err(e) = self
-                .try_run_migration(&**migration, i, operation_type.clone())
-                .await
-            {
-                self.trace_result(&**migration, &Err(e.clone()), operation_type.clone());
-                if retries == 0 {
-                    return Err(e);
+                self.import_migration_record(migration_record, existing_record)
+                    .await?;
+                match existing_record {
+                    Some(_) => import_report.overwritten.push(migration_id),
+                    None => import_report.imported.push(migration_id),
                 }
-                retries -= 1;
-                sleep(self.with_retries_per_migration.delay);
             }
+
+            for history_record in &state_export.history {
+                self.save_history_record(history_record).await?;
+                import_report.history_imported += 1;
+            }
+
+            Ok(import_report)
         }
+        .await;
+        let released = self.release_lock(lock_guard).await;
 
-        Ok(())
-    }
+        let import_report = res?;
+        released?;
 
-    pub async fn down(&self) -> Result<(), MigrationExecution> {
-        self.exec(
-            Range {
-                start: 0,
-                end: self.migrations.len(),
-            },
-            OperationType::Down,
-        )
-        .await
+        tracing::info!(
+            message = "migrations state was imported",
+            imported = format!("{:?}", import_report.imported),
+            overwritten = format!("{:?}", import_report.overwritten),
+            skipped = format!("{:?}", import_report.skipped)
+        );
+
+        Ok(import_report)
     }
 
-    async fn save_not_executed_migrations(
+    async fn import_migration_record(
         &self,
-        save_from_index: usize,
+        migration_record: MigrationRecord,
+        existing_record: Option<&MigrationRecord>,
     ) -> Result<(), MigrationExecution> {
-        if self.migrations.len() - 1 == save_from_index {
-            return Ok(());
-        }
-
-        for (i, migration) in self.migrations[save_from_index..].iter().enumerate() {
-            let migration_record = MigrationRecord::migration_start(migration.get_id().to_string());
-            let migration_record = MigrationRecord::migration_failed(migration_record);
-            let serialized_to_document_migration_record = bson::to_document(&migration_record)
-                .map_err(|error| MigrationExecution::InitialMigrationRecord {
-                    migration_id: migration.get_id().to_string(),
-                    migration_record: migration_record.clone(),
-                    next_not_executed_migrations_ids: self.get_not_executed_migrations_ids(i),
-                    additional_info: error,
-                })?;
+        let expected_version = existing_record.map_or(0, |record| record.version);
+        // exported records may come from an older migrator, they are applied to this target now
+        let migration_record = if migration_record.is_legacy() {
+            migration_record.upgraded(None)
+        } else {
+            migration_record
+        };
+        let migration_record = MigrationRecord {
+            version: expected_version + 1,
+            target: self.get_target().or(migration_record.target),
+            ..migration_record
+        };
 
-            self.with_connection
-                .db
-                .clone()
-                .collection::<MigrationRecord>(&self.get_collection_name())
-                .update_one(
-                    bson::doc! {"_id": &migration_record._id},
-                    bson::doc! {"$set": serialized_to_document_migration_record},
-                )
-                .upsert(true)
-                .await
-                .map_err(
-                    |error| MigrationExecution::FinishedButNotSavedDueMongoError {
-                        migration_id: migration.get_id().to_string(),
-                        migration_status: format!("{:?}", &migration_record.status),
-                        additional_info: error,
-                        next_not_executed_migrations_ids: self.get_not_executed_migrations_ids(i),
-                    },
-                )?;
+        let imported = self
+            .get_store()?
+            .mark(&migration_record, expected_version)
+            .await
+            .map_err(|error| MigrationExecution::StateNotImported {
+                migration_id: migration_record._id.clone(),
+                additional_info: error,
+            })?;
+        if !imported {
+            return Err(MigrationExecution::MigrationRecordModifiedConcurrently {
+                migration_id: migration_record._id,
+                expected_version,
+                next_not_executed_migrations_ids: vec![],
+            });
         }
 
         Ok(())
     }
 
-    /// Tries to up a migration from the passed before vec
-    pub async fn up_single_from_vec(&self, migration_id: String) -> Result<(), MigrationExecution> {
-        let migration = self
-            .migrations
-            .iter()
-            .enumerate()
-            .position(|(_index, migration)| migration.get_id() == migration_id);
+    /// Applies changes under the migrations lock, all of them share the same run id in the history
+    async fn apply_manual_changes(
+        &self,
+        changes: Vec<(String, ManualOperation)>,
+    ) -> Result<Vec<String>, MigrationExecution> {
+        let lock_guard = self.acquire_lock().await?;
+        let res = async {
+            self.upgrade_state_format().await?;
+            let run_id = ObjectId::new().to_hex();
+            let ids = changes
+                .iter()
+                .map(|(id, _)| id.clone())
+                .collect::<Vec<String>>();
+            let migration_records = self.load_migration_records(&ids).await?;
 
-        if let Some(i) = migration {
-            self.exec(
-                Range {
-                    start: i,
-                    end: i + 1,
-                },
-                OperationType::Up,
-            )
-            .await
-        } else {
-            Err(MigrationExecution::MigrationFromVecNotFound { migration_id })
+            for (migration_id, manual_operation) in changes {
+                tracing::info!(
+                    id = migration_id,
+                    manual = format!("{:?}", manual_operation),
+                    run_id = run_id
+                );
+                self.apply_manual_change(
+                    &migration_id,
+                    manual_operation,
+                    migration_records.get(&migration_id),
+                    &run_id,
+                )
+                .await?;
+            }
+
+            Ok(ids)
         }
+        .await;
+        let released = self.release_lock(lock_guard).await;
+
+        let ids = res?;
+        released?;
+
+        Ok(ids)
     }
 
-    /// Tries do rollback a migration from the bassed before vec
-    pub async fn down_single_from_vec(
+    async fn apply_manual_change(
         &self,
-        migration_id: String,
+        migration_id: &str,
+        manual_operation: ManualOperation,
+        previous_migration_record: Option<&MigrationRecord>,
+        run_id: &str,
     ) -> Result<(), MigrationExecution> {
-        let migration = self
-            .migrations
-            .iter()
-            .enumerate()
-            .position(|(_index, migration)| migration.get_id() == migration_id);
+        let expected_version = previous_migration_record.map_or(0, |record| record.version);
+        let store = self.get_store()?;
+        let not_saved = |error| MigrationExecution::ManualChangeNotSaved {
+            migration_id: migration_id.to_string(),
+            additional_info: error,
+        };
+        let modified_concurrently = || MigrationExecution::MigrationRecordModifiedConcurrently {
+            migration_id: migration_id.to_string(),
+            expected_version,
+            next_not_executed_migrations_ids: vec![],
+        };
 
-        if let Some(i) = migration {
-            self.exec(
-                Range {
-                    start: i,
-                    end: i + 1,
-                },
-                OperationType::Down,
-            )
-            .await
-        } else {
-            Err(MigrationExecution::MigrationFromVecNotFound { migration_id })
-        }
+        let migration_record = match manual_operation {
+            ManualOperation::Forget => {
+                let Some(previous_migration_record) = previous_migration_record else {
+                    return Ok(());
+                };
+
+                let forgotten = store
+                    .forget(migration_id, expected_version)
+                    .await
+                    .map_err(not_saved)?;
+                if !forgotten {
+                    return Err(modified_concurrently());
+                }
+
+                // the history keeps the forgotten state with the date it was forgotten
+                let now = Utc::now();
+                MigrationRecord {
+                    start_date: Some(now),
+                    end_date: Some(now),
+                    duration: Some(0),
+                    ..previous_migration_record.clone()
+                }
+                .changed_manually(manual_operation.clone())
+            }
+            _ => {
+                let migration_record = MigrationRecord::migration_start(migration_id.to_string())
+                    .applied_to(self.get_target())
+                    .after(previous_migration_record);
+                // a change by hand isn't an attempt to execute the migration
+                let migration_record = MigrationRecord {
+                    attempts: previous_migration_record.map_or(0, |record| record.attempts),
+                    ..migration_record
+                };
+                let migration_record = match manual_operation {
+                    ManualOperation::MarkFailed => migration_record.migration_failed(),
+                    _ => migration_record.migration_succeeded().with_checksum(
+                        self.migrations
+                            .iter()
+                            .find(|migration| migration.get_id() == migration_id)
+                            .and_then(|migration| migration.checksum()),
+                    ),
+                }
+                .changed_manually(manual_operation.clone());
+
+                let marked = store
+                    .mark(&migration_record, expected_version)
+                    .await
+                    .map_err(not_saved)?;
+                if !marked {
+                    return Err(modified_concurrently());
+                }
+
+                migration_record
+            }
+        };
+
+        self.save_history_record(&MigrationHistoryRecord::manual_change(
+            &migration_record,
+            run_id.to_string(),
+            manual_operation,
+        ))
+        .await
     }
 
+    /// Returns migrations ordered by their dependencies
     #[allow(clippy::result_large_err)]
-    fn validate(&self) -> Result<(), MigrationExecution> {
+    fn validate(&self) -> Result<Vec<&dyn Migration>, MigrationExecution> {
         let mut entries = BTreeMap::new();
         self.migrations
             .iter()
@@ -284,188 +1653,521 @@ impl WithMigrationsVec {
         if !duplicates.is_empty() {
             Err(MigrationExecution::PassedMigrationsWithDuplicatedIds { duplicates })
         } else {
-            Ok(())
+            dependencies::order_by_dependencies(&self.migrations)
         }
     }
 
     #[allow(clippy::result_large_err)]
-    fn prepare_initial_migration_record(
+    async fn load_migration_record(
         &self,
         migration: &dyn Migration,
-        i: usize,
-    ) -> Result<(Document, MigrationRecord), MigrationExecution> {
-        let migration_record = MigrationRecord::migration_start(migration.get_id().to_string());
-
-        Ok((
-            bson::to_document(&migration_record).map_err(|error| {
-                MigrationExecution::InitialMigrationRecord {
-                    migration_id: migration.get_id().to_string(),
-                    migration_record: migration_record.clone(),
-                    next_not_executed_migrations_ids: self.get_not_executed_migrations_ids(i),
-                    additional_info: error,
-                }
-            })?,
-            migration_record,
-        ))
+        next_not_executed_migrations_ids: &[String],
+    ) -> Result<Option<MigrationRecord>, MigrationExecution> {
+        self.get_store()?
+            .load_record(migration.get_id())
+            .await
+            .map_err(|error| MigrationExecution::InProgressStatusNotSaved {
+                migration_id: migration.get_id().to_string(),
+                additional_info: error,
+                next_not_executed_migrations_ids: next_not_executed_migrations_ids.to_vec(),
+            })
     }
 
+    /// Saves the record only if nobody has modified it since it was loaded,
+    /// a record exists already if the migration was executed before, e.g. failed or rolled back
     async fn save_initial_migration_record(
         &self,
         migration: &dyn Migration,
-        serialized_to_document_migration_record: Document,
-        i: usize,
-    ) -> Result<InsertOneResult, MigrationExecution> {
-        self.with_connection
-            .db
-            .clone()
-            .collection(&self.get_collection_name())
-            .insert_one(serialized_to_document_migration_record)
+        migration_record: &MigrationRecord,
+        expected_version: i64,
+        next_not_executed_migrations_ids: &[String],
+    ) -> Result<(), MigrationExecution> {
+        let saved = self
+            .get_store()?
+            .begin(migration_record, expected_version)
             .await
             .map_err(|error| MigrationExecution::InProgressStatusNotSaved {
                 migration_id: migration.get_id().to_string(),
                 additional_info: error,
-                next_not_executed_migrations_ids: self.get_not_executed_migrations_ids(i),
-            })
+                next_not_executed_migrations_ids: next_not_executed_migrations_ids.to_vec(),
+            })?;
+
+        if !saved {
+            return Err(MigrationExecution::MigrationRecordModifiedConcurrently {
+                migration_id: migration.get_id().to_string(),
+                expected_version,
+                next_not_executed_migrations_ids: next_not_executed_migrations_ids.to_vec(),
+            });
+        }
+
+        Ok(())
     }
 
+    /// Saves the record only if it's still the one saved when the migration was started
     async fn save_executed_migration_record(
         &self,
         migration: &dyn Migration,
         migration_record: &MigrationRecord,
-        serialized_to_document_migration_record: Document,
-        res: InsertOneResult,
-        i: usize,
+        expected_version: i64,
+        next_not_executed_migrations_ids: &[String],
     ) -> Result<(), MigrationExecution> {
-        self.with_connection
-            .db
-            .clone()
-            .collection::<MigrationRecord>(&self.get_collection_name())
-            .update_one(
-                bson::doc! {"_id": res.inserted_id},
-                bson::doc! {"$set": serialized_to_document_migration_record},
-            )
-            .upsert(true)
+        let saved = self
+            .get_store()?
+            .finish(migration_record, expected_version, None)
             .await
             .map_err(
                 |error| MigrationExecution::FinishedButNotSavedDueMongoError {
                     migration_id: migration.get_id().to_string(),
                     migration_status: format!("{:?}", &migration_record.status),
                     additional_info: error,
-                    next_not_executed_migrations_ids: self.get_not_executed_migrations_ids(i),
+                    next_not_executed_migrations_ids: next_not_executed_migrations_ids.to_vec(),
                 },
             )?;
 
+        if !saved {
+            return Err(MigrationExecution::MigrationRecordModifiedConcurrently {
+                migration_id: migration.get_id().to_string(),
+                expected_version,
+                next_not_executed_migrations_ids: next_not_executed_migrations_ids.to_vec(),
+            });
+        }
+
         Ok(())
     }
 
     fn try_get_mongo_shell(&self) -> Option<Shell> {
-        if self.with_shell_config.is_some() {
-            Some(Shell {
-                config: self
-                    .with_shell_config
-                    .clone()
-                    .expect("shell config is present")
-                    .with_shell_config,
-            })
-        } else {
-            None
+        self.shell_config.clone().map(|config| Shell { config })
+    }
+
+    fn make_env(
+        &self,
+        migration: &dyn Migration,
+        run_id: &str,
+        session: Option<Arc<Mutex<ClientSession>>>,
+        cancellation: CancellationToken,
+    ) -> Env {
+        Env {
+            db: self.db.clone(),
+            client: self.db.as_ref().map(|db| db.client().clone()),
+            shell: self.try_get_mongo_shell(),
+            session,
+            migration_id: migration.get_id().to_string(),
+            run_id: run_id.to_string(),
+            cancellation,
+            progress: ProgressReporter::new(
+                migration.get_id().to_string(),
+                run_id.to_string(),
+                self.progress_handler.clone(),
+            ),
+            extensions: self.extensions.clone(),
         }
     }
 
-    async fn up_migration(
+    async fn up_migration(&self, migration: &dyn Migration, env: Env) -> anyhow::Result<()> {
+        migration.up(env).await
+    }
+
+    async fn down_migration(&self, migration: &dyn Migration, env: Env) -> anyhow::Result<()> {
+        migration.down(env).await
+    }
+
+    /// Transactions are available only on a replica set or a sharded cluster,
+    /// so the topology is checked before the transaction is started
+    async fn start_transaction(
         &self,
         migration: &dyn Migration,
-        shell: Option<Shell>,
-        migration_record: &MigrationRecord,
-    ) -> MigrationRecord {
-        migration
-            .up(Env {
-                db: Some(self.with_connection.db.clone()),
-                shell,
-            })
+        next_not_executed_migrations_ids: &[String],
+    ) -> Result<ClientSession, MigrationExecution> {
+        let not_started = |error| MigrationExecution::TransactionNotStarted {
+            migration_id: migration.get_id().to_string(),
+            next_not_executed_migrations_ids: next_not_executed_migrations_ids.to_vec(),
+            additional_info: error,
+        };
+
+        let not_supported = || MigrationExecution::TransactionsNotSupported {
+            migration_id: migration.get_id().to_string(),
+            next_not_executed_migrations_ids: next_not_executed_migrations_ids.to_vec(),
+        };
+        let Some(db) = &self.db else {
+            return Err(not_supported());
+        };
+        let not_supported_by_store = || MigrationExecution::TransactionsNotSupportedByStore {
+            migration_id: migration.get_id().to_string(),
+            next_not_executed_migrations_ids: next_not_executed_migrations_ids.to_vec(),
+        };
+        // the record is committed by the mongo store, which ignores sessions of custom ones
+        if self.store.is_some() {
+            return Err(not_supported_by_store());
+        }
+
+        let hello = db
+            .run_command(bson::doc! {"hello": 1})
             .await
-            .map_or_else(
-                |_| migration_record.clone().migration_failed(),
-                |_| migration_record.clone().migration_succeeded(),
-            )
+            .map_err(not_started)?;
+        if !hello.contains_key("setName") && hello.get_str("msg") != Ok("isdbgrid") {
+            return Err(not_supported());
+        }
+
+        let mut session = db.client().start_session().await.map_err(not_started)?;
+        // the driver rejects a session of another client, so the state db has to share it
+        if let Some(state_db) = &self.state_db {
+            match state_db
+                .run_command(bson::doc! {"ping": 1})
+                .session(&mut session)
+                .await
+            {
+                Ok(_) => {}
+                Err(error) if matches!(*error.kind, ErrorKind::InvalidArgument { .. }) => {
+                    return Err(not_supported_by_store());
+                }
+                Err(error) => return Err(not_started(error)),
+            }
+        }
+        session.start_transaction().await.map_err(not_started)?;
+
+        Ok(session)
     }
 
-    async fn down_migration(
+    /// Commits writes of a successful migration together with its record,
+    /// otherwise aborts them and returns the migration result as is
+    async fn complete_transaction(
         &self,
-        migration: &dyn Migration,
-        shell: Option<Shell>,
+        session: &Mutex<ClientSession>,
+        migration_result: Option<anyhow::Result<()>>,
+        succeeded_migration_record: &MigrationRecord,
+        expected_version: i64,
+    ) -> Option<anyhow::Result<()>> {
+        let mut session = session.lock().await;
+
+        let res = match migration_result {
+            Some(Ok(())) => {
+                self.commit_transaction(&mut session, succeeded_migration_record, expected_version)
+                    .await
+            }
+            migration_result => {
+                if let Err(error) = session.abort_transaction().await {
+                    tracing::warn!(
+                        id = succeeded_migration_record._id,
+                        "failed to abort transaction: {error}"
+                    );
+                }
+                return migration_result;
+            }
+        };
+
+        if res.is_err() {
+            // the transaction might be aborted already by the server
+            let _ = session.abort_transaction().await;
+        }
+
+        Some(res)
+    }
+
+    async fn commit_transaction(
+        &self,
+        session: &mut ClientSession,
         migration_record: &MigrationRecord,
+        expected_version: i64,
+    ) -> anyhow::Result<()> {
+        let saved = self
+            .get_store()?
+            .finish(migration_record, expected_version, Some(&mut *session))
+            .await?;
+
+        if !saved {
+            return Err(MigrationExecution::MigrationRecordModifiedConcurrently {
+                migration_id: migration_record._id.clone(),
+                expected_version,
+                next_not_executed_migrations_ids: vec![],
+            }
+            .into());
+        }
+
+        session.commit_transaction().await?;
+
+        Ok(())
+    }
+
+    fn finish_migration_record(
+        &self,
+        migration: &dyn Migration,
+        migration_record: MigrationRecord,
+        migration_result: &Option<anyhow::Result<()>>,
+        operation_type: &OperationType,
     ) -> MigrationRecord {
-        migration
-            .down(Env {
-                db: Some(self.with_connection.db.clone()),
-                shell,
-            })
-            .await
-            .map_or_else(
-                |_| migration_record.clone().migration_failed(),
-                |_| migration_record.clone().migration_succeeded(),
-            )
+        match (migration_result, operation_type) {
+            (Some(Ok(())), OperationType::Up) => migration_record
+                .migration_succeeded()
+                .with_checksum(migration.checksum()),
+            (Some(Ok(())), OperationType::Down) => migration_record.migration_rolled_back(),
+            // a rollback which wasn't completed leaves the migration applied
+            (migration_result, OperationType::Down) => migration_record
+                .migration_rollback_failed(
+                    migration_result
+                        .as_ref()
+                        .and_then(|migration_result| migration_result.as_ref().err()),
+                )
+                .with_checksum(migration.checksum()),
+            (Some(Err(error)), OperationType::Up) => {
+                migration_record.migration_failed_with_error(error)
+            }
+            (None, OperationType::Up) => migration_record.migration_timed_out(),
+        }
+        .next_version()
     }
 
     async fn try_run_migration(
         &self,
         migration: &dyn Migration,
-        i: usize,
+        next_not_executed_migrations_ids: &[String],
         operation_type: OperationType,
-    ) -> Result<(), MigrationExecution> {
+        run_id: &str,
+        attempt: u32,
+        retry_policy: &RetryPolicy,
+    ) -> Result<MigrationRecord, AttemptFailure> {
         tracing::info!(
             id = migration.get_id(),
             op = format!("{:?}", operation_type),
             status = format!("{:?}", MigrationStatus::InProgress)
         );
 
-        let (serialized_to_document_migration_record, migration_record) =
-            self.prepare_initial_migration_record(migration, i)?;
-
-        let res = self
-            .save_initial_migration_record(migration, serialized_to_document_migration_record, i)
+        let previous_migration_record = self
+            .load_migration_record(migration, next_not_executed_migrations_ids)
             .await?;
+        let migration_record = MigrationRecord::migration_start(migration.get_id().to_string())
+            .applied_to(self.get_target())
+            .owned_by(self.owner.clone())
+            .after(previous_migration_record.as_ref());
+
+        // the in progress record is saved outside of the transaction so that it's visible to others
+        self.save_initial_migration_record(
+            migration,
+            &migration_record,
+            previous_migration_record.map_or(0, |record| record.version),
+            next_not_executed_migrations_ids,
+        )
+        .await?;
+
+        let history_record = MigrationHistoryRecord::attempt_start(
+            &migration_record,
+            run_id.to_string(),
+            operation_type.clone(),
+            attempt,
+        );
+        self.save_history_record(&history_record).await?;
+
+        let session = if migration.transactional() {
+            match self
+                .start_transaction(migration, next_not_executed_migrations_ids)
+                .await
+            {
+                Ok(session) => Some(Arc::new(Mutex::new(session))),
+                Err(e) => {
+                    return Err(self
+                        .save_not_started_attempt(
+                            migration,
+                            migration_record,
+                            history_record,
+                            &operation_type,
+                            next_not_executed_migrations_ids,
+                            e,
+                        )
+                        .await)
+                }
+            }
+        } else {
+            None
+        };
+
+        // the transaction saves the record on commit and the server aborts it with a write conflict
+        // if the record was prolonged in the meantime, so transactional migrations aren't prolonged
+        let store = self.get_store()?;
+        let _heartbeat = session.is_none().then(|| {
+            stale::start_heartbeat(
+                store,
+                &migration_record,
+                self.stale_config.heartbeat_interval,
+            )
+        });
+
+        let cancellation = self.cancellation_token.child_token();
+        let env = self.make_env(migration, run_id, session.clone(), cancellation.clone());
+        let timeout = migration.timeout().or(self.default_timeout);
 
-        let shell = self.try_get_mongo_shell();
+        let migration_future = async {
+            match operation_type {
+                OperationType::Up => self.up_migration(migration, env).await,
+                OperationType::Down => self.down_migration(migration, env).await,
+            }
+        };
+        // None means the migration was cancelled since its timeout was elapsed
+        let migration_result = match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, migration_future).await.ok(),
+            None => Some(migration_future.await),
+        };
+        if migration_result.is_none() {
+            // tasks spawned by the cancelled migration may still be running
+            cancellation.cancel();
+        }
 
-        let migration_record = match operation_type {
-            OperationType::Up => self.up_migration(migration, shell, &migration_record).await,
-            OperationType::Down => {
-                self.down_migration(migration, shell, &migration_record)
+        let started_version = migration_record.version;
+        let (migration_result, migration_record) = match &session {
+            Some(session) => {
+                let succeeded_migration_record = self.finish_migration_record(
+                    migration,
+                    migration_record.clone(),
+                    &Some(Ok(())),
+                    &operation_type,
+                );
+                match self
+                    .complete_transaction(
+                        session,
+                        migration_result,
+                        &succeeded_migration_record,
+                        started_version,
+                    )
                     .await
+                {
+                    Some(Ok(())) => (Some(Ok(())), succeeded_migration_record),
+                    migration_result => {
+                        let migration_record = self.finish_migration_record(
+                            migration,
+                            migration_record,
+                            &migration_result,
+                            &operation_type,
+                        );
+                        (migration_result, migration_record)
+                    }
+                }
+            }
+            None => {
+                let migration_record = self.finish_migration_record(
+                    migration,
+                    migration_record,
+                    &migration_result,
+                    &operation_type,
+                );
+                (migration_result, migration_record)
             }
         };
 
-        let serialized_to_document_migration_record = bson::to_document(&migration_record)
-            .map_err(
-                |error| MigrationExecution::FinishedButNotSavedDueToSerialization {
-                    migration_id: migration.get_id().to_string(),
-                    migration_status: format!("{:?}", &migration_record.status),
-                    migration_record: migration_record.clone(),
-                    next_not_executed_migrations_ids: self.get_not_executed_migrations_ids(i),
-                    additional_info: error,
-                },
-            )?;
+        // the migration has run, so failed writes of its result are retried without executing it again,
+        // the record goes first since it decides whether the migration is executed by the next runs
+        let committed = session.is_some() && matches!(migration_result, Some(Ok(())));
+        if !committed {
+            self.save_with_retries(retry_policy, || {
+                self.save_executed_migration_record(
+                    migration,
+                    &migration_record,
+                    started_version,
+                    next_not_executed_migrations_ids,
+                )
+            })
+            .await
+            .map_err(AttemptFailure::after_run)?;
+        }
+        let finished_history_record = history_record.attempt_finished(&migration_record);
+        self.save_with_retries(retry_policy, || {
+            self.save_history_record(&finished_history_record)
+        })
+        .await
+        .map_err(AttemptFailure::after_run)?;
+
+        let failure = match migration_result {
+            Some(Ok(())) => return Ok(migration_record),
+            Some(Err(error)) => MigrationExecution::FinishedAndSavedAsFail {
+                migration_id: migration.get_id().to_string(),
+                next_not_executed_migrations_ids: next_not_executed_migrations_ids.to_vec(),
+                error: Arc::new(error),
+            },
+            None => MigrationExecution::TimedOut {
+                migration_id: migration.get_id().to_string(),
+                timeout: timeout.unwrap_or_default(),
+                next_not_executed_migrations_ids: next_not_executed_migrations_ids.to_vec(),
+            },
+        };
+
+        // not executed rollbacks leave migrations applied so there is nothing to save
+        if operation_type == OperationType::Up {
+            self.save_not_executed_migrations(next_not_executed_migrations_ids)
+                .await
+                .map_err(AttemptFailure::after_run)?;
+        }
 
-        self.save_executed_migration_record(
+        Err(failure.into())
+    }
+
+    /// Saves the attempt which has failed before the migration was executed like other failed ones,
+    /// the migration wasn't executed, so the attempt can be retried as a whole
+    async fn save_not_started_attempt(
+        &self,
+        migration: &dyn Migration,
+        migration_record: MigrationRecord,
+        history_record: MigrationHistoryRecord,
+        operation_type: &OperationType,
+        next_not_executed_migrations_ids: &[String],
+        error: MigrationExecution,
+    ) -> AttemptFailure {
+        let started_version = migration_record.version;
+        let migration_record = self.finish_migration_record(
             migration,
-            &migration_record,
-            serialized_to_document_migration_record,
-            res,
-            i,
-        )
-        .await?;
+            migration_record,
+            &Some(Err(anyhow::Error::new(error.clone()))),
+            operation_type,
+        );
 
-        if migration_record.status == MigrationStatus::Fail {
-            self.save_not_executed_migrations(i + 1).await?;
-            return Err(MigrationExecution::FinishedAndSavedAsFail {
-                migration_id: migration.get_id().to_string(),
-                next_not_executed_migrations_ids: self.get_not_executed_migrations_ids(i),
-            });
+        let saved = async {
+            self.save_executed_migration_record(
+                migration,
+                &migration_record,
+                started_version,
+                next_not_executed_migrations_ids,
+            )
+            .await?;
+            self.save_history_record(&history_record.attempt_finished(&migration_record))
+                .await?;
+            // not executed rollbacks leave migrations applied so there is nothing to save
+            if *operation_type == OperationType::Up {
+                self.save_not_executed_migrations(next_not_executed_migrations_ids)
+                    .await?;
+            }
+
+            Ok::<_, MigrationExecution>(())
         }
+        .await;
 
-        Ok(())
+        saved.err().unwrap_or(error).into()
+    }
+
+    /// Retries a write of a finished attempt on transient errors according to the retry policy
+    async fn save_with_retries<F, Fut>(
+        &self,
+        retry_policy: &RetryPolicy,
+        save: F,
+    ) -> Result<(), MigrationExecution>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<(), MigrationExecution>>,
+    {
+        let started_at = Instant::now();
+        let mut attempt = 1;
+
+        loop {
+            let Err(e) = save().await else {
+                return Ok(());
+            };
+
+            let delay = if with_retries::is_transient(&e) {
+                retry_policy.next_delay(attempt, started_at.elapsed(), &e)
+            } else {
+                None
+            };
+            let Some(delay) = delay else {
+                return Err(e);
+            };
+            attempt += 1;
+            tokio::time::sleep(delay).await;
+        }
     }
 
     fn trace_result(
@@ -489,8 +2191,55 @@ impl WithMigrationsVec {
     }
 }
 
-#[derive(Debug, Clone)]
-enum OperationType {
-    Up,
-    Down,
+/// Failed migrations which have to succeed before the migration is executed:
+/// its dependencies for up and migrations depending on it for down
+fn blocked_by(
+    migration: &dyn Migration,
+    migrations: &[&dyn Migration],
+    operation_type: &OperationType,
+    failed: &BTreeSet<String>,
+) -> Vec<String> {
+    match operation_type {
+        OperationType::Up => migration
+            .depends_on()
+            .into_iter()
+            .filter(|dependency| failed.contains(dependency))
+            .collect(),
+        OperationType::Down => migrations
+            .iter()
+            .filter(|dependent| {
+                failed.contains(dependent.get_id())
+                    && dependent
+                        .depends_on()
+                        .iter()
+                        .any(|dependency| dependency == migration.get_id())
+            })
+            .map(|dependent| dependent.get_id().to_string())
+            .collect(),
+    }
+}
+
+/// A failed attempt, it isn't retried when the migration has already run
+/// but its result wasn't saved, otherwise the migration would be executed twice
+struct AttemptFailure {
+    error: MigrationExecution,
+    retryable: bool,
+}
+
+impl AttemptFailure {
+    fn after_run(error: MigrationExecution) -> Self {
+        AttemptFailure {
+            error,
+            retryable: false,
+        }
+    }
+}
+
+impl From<MigrationExecution> for AttemptFailure {
+    fn from(error: MigrationExecution) -> Self {
+        AttemptFailure {
+            error,
+            retryable: true,
+        }
+    }
 }
@@ -1,30 +1,134 @@
-//! With this type of the migrator it's possible to try run failed migrations multiple times
-use std::time::Duration;
+//! [`RetryPolicy`] makes it possible to try run failed migrations multiple times
+use std::{sync::Arc, time::Duration};
 
-use super::{with_connection::WithConnection, with_migrations_vec::WithMigrationsVec};
-use crate::migration::Migration;
+use mongodb::error::{Error as MongoDbError, ErrorKind};
+use rand::Rng;
 
+use crate::error::MigrationExecution;
+
+/// Describes when and how often a failed migration is retried
 #[derive(Clone)]
-pub struct WithRetries {
-    pub with_connection: WithConnection,
-    pub with_retries_per_migration: Retry,
+pub struct RetryPolicy {
+    /// How many times a failed migration is retried, 0 means it isn't retried at all
+    pub max_retries: usize,
+    /// The delay before the first retry
+    pub initial_delay: Duration,
+    /// Every next delay is the previous one multiplied by it
+    pub multiplier: f64,
+    pub max_delay: Duration,
+    /// A delay is randomly changed within +-jitter share of it, e.g. 0.1 means +-10%,
+    /// it's clamped to [0, 1]
+    pub jitter: f64,
+    /// No retries are made once it's elapsed since the first attempt
+    pub max_elapsed: Option<Duration>,
+    /// Decides whether an error is worth retrying
+    pub is_retryable: Arc<dyn Fn(&MigrationExecution) -> bool + Send + Sync>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 0,
+            initial_delay: Duration::from_millis(100),
+            multiplier: 2.0,
+            max_delay: Duration::from_secs(30),
+            jitter: 0.1,
+            max_elapsed: None,
+            is_retryable: Arc::new(is_transient),
+        }
+    }
 }
 
-#[derive(Clone, Default)]
-pub struct Retry {
-    pub count: usize,
-    pub delay: Duration,
+impl RetryPolicy {
+    /// Retries any error `count` times with the same delay
+    pub fn fixed(count: usize, delay: Duration) -> Self {
+        Self {
+            max_retries: count,
+            initial_delay: delay,
+            multiplier: 1.0,
+            max_delay: delay,
+            jitter: 0.0,
+            max_elapsed: None,
+            is_retryable: Arc::new(|_| true),
+        }
+    }
+
+    /// Retries only transient errors with exponentially growing delays
+    pub fn exponential(max_retries: usize, initial_delay: Duration) -> Self {
+        Self {
+            max_retries,
+            initial_delay,
+            ..Default::default()
+        }
+    }
+
+    /// Returns a delay before the next attempt or `None` if the migration shouldn't be retried.
+    /// `attempt` is the number of the failed attempt starting from 1
+    pub fn next_delay(
+        &self,
+        attempt: u32,
+        elapsed: Duration,
+        error: &MigrationExecution,
+    ) -> Option<Duration> {
+        if attempt as usize > self.max_retries || !(self.is_retryable)(error) {
+            return None;
+        }
+
+        // computed in seconds since the grown delay may not fit into a Duration
+        let max_delay = self.max_delay.as_secs_f64();
+        let delay = (self.initial_delay.as_secs_f64()
+            * self
+                .multiplier
+                .powi(attempt.min(i32::MAX as u32) as i32 - 1))
+        .min(max_delay);
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        let delay = if jitter > 0.0 {
+            delay * (1.0 + rand::thread_rng().gen_range(-jitter..=jitter))
+        } else {
+            delay
+        };
+        let delay = Duration::try_from_secs_f64(delay).unwrap_or(self.max_delay);
+
+        match self.max_elapsed {
+            Some(max_elapsed) if elapsed + delay > max_elapsed => None,
+            _ => Some(delay),
+        }
+    }
 }
 
-impl WithRetries {
-    pub fn with_migrations_vec(self, migrations: Vec<Box<dyn Migration>>) -> WithMigrationsVec {
-        WithMigrationsVec {
-            migrations,
-            // TODO(kakoc): rework forwarding: merge? split? -clone?
-            with_shell_config: None,
-            with_connection: self.with_connection,
-            with_retries_per_migration: self.with_retries_per_migration,
-            collection_name: None,
+/// Treats as transient only MongoDB errors which are labeled as retryable by the server
+/// and network errors. Writes which fail after a migration has run are retried by themselves,
+/// the migration isn't executed again regardless of the predicate
+pub fn is_transient(error: &MigrationExecution) -> bool {
+    match error {
+        MigrationExecution::FinishedAndSavedAsFail { error, .. } => error
+            .chain()
+            .filter_map(|cause| cause.downcast_ref::<MongoDbError>())
+            .any(is_transient_mongodb_error),
+        MigrationExecution::InProgressStatusNotSaved {
+            additional_info, ..
+        }
+        | MigrationExecution::FinishedButNotSavedDueMongoError {
+            additional_info, ..
+        }
+        | MigrationExecution::HistoryRecordNotSaved {
+            additional_info, ..
         }
+        | MigrationExecution::MigrationRecordsNotLoaded { additional_info } => additional_info
+            .as_mongodb_error()
+            .is_some_and(is_transient_mongodb_error),
+        MigrationExecution::TransactionNotStarted {
+            additional_info, ..
+        } => is_transient_mongodb_error(additional_info),
+        _ => false,
     }
 }
+
+fn is_transient_mongodb_error(error: &MongoDbError) -> bool {
+    error.contains_label("RetryableWriteError")
+        || error.contains_label("TransientTransactionError")
+        || matches!(
+            *error.kind,
+            ErrorKind::Io(_) | ErrorKind::ConnectionPoolCleared { .. }
+        )
+}
@@ -0,0 +1,22 @@
+//! Describes in which direction a migration is executed
+//! and how a migration state was changed by hand
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub enum OperationType {
+    /// [`crate::migration::Migration::up`] is executed
+    Up,
+    /// [`crate::migration::Migration::down`] is executed
+    Down,
+}
+
+/// A change of a migration state made by hand instead of executing the migration
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub enum ManualOperation {
+    /// The migration was already applied when the migrator was adopted
+    Baseline,
+    MarkApplied,
+    MarkFailed,
+    /// The migration record was removed, so the migration is pending again
+    Forget,
+}
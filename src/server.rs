@@ -41,6 +41,7 @@ impl Default for ServiceParams {
                 db: DbParams {
                     connection_string: "mongodb://localhost:27017".to_string(),
                     log_into_db_name: "test".to_string(),
+                    target_db_name: None,
                 },
                 migrations: vec![],
             },
@@ -51,7 +52,10 @@ impl Default for ServiceParams {
 
 pub struct DbParams {
     pub connection_string: String,
+    /// Keeps migration records
     pub log_into_db_name: String,
+    /// The database migrations are applied to, the same as the records one when absent
+    pub target_db_name: Option<String>,
 }
 
 pub async fn server(params: ServiceParams) {
@@ -118,14 +122,20 @@ fn init_routing(shared_state: SharedState) -> Router {
 }
 
 async fn init_migrator(params: MigratorParams) -> WithMigrationsVec {
-    DefaultMigrator::new()
-        .with_conn(
-            mongodb::Client::with_uri_str(params.db.connection_string)
-                .await
-                .expect("mongodb client created")
-                .database(&params.db.log_into_db_name),
-        )
-        .with_migrations_vec(params.migrations)
+    let client = mongodb::Client::with_uri_str(params.db.connection_string)
+        .await
+        .expect("mongodb client created");
+    let migrator = DefaultMigrator::new();
+
+    match params.db.target_db_name {
+        // records of every target are kept in its own collection of the central database
+        Some(target_db_name) => migrator
+            .with_conn(client.database(&target_db_name))
+            .with_state_db(client.database(&params.db.log_into_db_name))
+            .with_collection_name(format!("{target_db_name}_migrations")),
+        None => migrator.with_conn(client.database(&params.db.log_into_db_name)),
+    }
+    .with_migrations_vec(params.migrations)
 }
 
 async fn run_server(router: Router, port: u16) {
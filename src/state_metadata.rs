@@ -0,0 +1,34 @@
+//! [`StateMetadata`] describes the document which is stored next to migration records
+//! and tells which format the records are written in.  
+//! Records of an older format are upgraded the first time a newer migrator executes migrations
+use chrono::{DateTime, Utc};
+use serde_derive::{Deserialize, Serialize};
+
+/// 1 - records of 0.1.x without a version, attempts and a target
+/// 2 - versioned records which know their target
+pub const STATE_FORMAT_VERSION: u32 = 2;
+
+pub const STATE_METADATA_ID: &str = "state";
+
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct StateMetadata {
+    pub _id: String,
+    pub format_version: u32,
+    /// When records were upgraded to the format version
+    #[serde(default)]
+    pub upgraded_at: Option<DateTime<Utc>>,
+    /// The migrator which upgraded records
+    #[serde(default)]
+    pub upgraded_by: Option<String>,
+}
+
+impl StateMetadata {
+    pub fn current(upgraded_by: String) -> Self {
+        StateMetadata {
+            _id: STATE_METADATA_ID.to_string(),
+            format_version: STATE_FORMAT_VERSION,
+            upgraded_at: Some(Utc::now()),
+            upgraded_by: Some(upgraded_by),
+        }
+    }
+}
@@ -0,0 +1,66 @@
+//! These tests check that settings passed to the builder in any order reach the runner
+use std::time::Duration;
+
+use mongodb_migrator::{
+    migration::Migration,
+    migration_record::MigrationRecord,
+    migration_status::MigrationStatus,
+    migrator::{
+        execution_strategy::ExecutionStrategy, shell::ShellConfig, DefaultMigrator, Migrator,
+    },
+};
+
+use super::utils::{TestDb, M0, M1, M3};
+
+pub async fn settings_passed_in_any_order_reach_runner(t: &TestDb) {
+    let migrations: Vec<Box<dyn Migration>> =
+        vec![Box::new(M0 {}), Box::new(M3 {}), Box::new(M1 {})];
+
+    let migrator = Migrator::builder()
+        .with_retries(1, Duration::from_millis(1))
+        .with_collection_name("custom_migrations")
+        .with_shell_config(ShellConfig::default())
+        .with_conn(t.db.clone())
+        .with_execution_strategy(ExecutionStrategy::TryAll)
+        .build(migrations);
+
+    assert_eq!(migrator.retry_policy.max_retries, 1);
+    assert!(migrator.shell_config.is_some());
+
+    assert!(migrator.up().await.is_err());
+
+    let statuses = [M0 {}.get_id(), M3 {}.get_id(), M1 {}.get_id()]
+        .into_iter()
+        .map(|migration_id| async move {
+            t.db.collection::<MigrationRecord>("custom_migrations")
+                .find_one(bson::doc! {"_id": migration_id})
+                .await
+                .unwrap()
+                .unwrap()
+                .status
+        });
+    assert_eq!(
+        futures::future::join_all(statuses).await,
+        vec![
+            MigrationStatus::Success,
+            MigrationStatus::Fail,
+            MigrationStatus::Success
+        ]
+    );
+    let history = migrator
+        .get_history_by_migration_id(M3 {}.get_id())
+        .await
+        .unwrap();
+    assert_eq!(history.len(), 2);
+}
+
+pub async fn shell_config_and_retries_are_kept_together(t: &TestDb) {
+    let migrator = DefaultMigrator::new()
+        .with_conn(t.db.clone())
+        .with_shell_config(ShellConfig::default())
+        .with_retries(3, Duration::from_millis(1))
+        .with_migrations_vec(vec![]);
+
+    assert!(migrator.shell_config.is_some());
+    assert_eq!(migrator.retry_policy.max_retries, 3);
+}
@@ -0,0 +1,72 @@
+//! These tests check that changes of already applied migrations are noticed
+use anyhow::Result;
+use async_trait::async_trait;
+use mongodb_migrator::{
+    error::MigrationExecution,
+    migration::Migration,
+    migration_record::MigrationRecord,
+    migrator::{
+        checksum::{calc_checksum, ChecksumPolicy},
+        Env,
+    },
+};
+
+use super::utils::{init_migrator_with_migrations, TestDb};
+
+struct Script {
+    script: &'static str,
+}
+
+#[async_trait]
+impl Migration for Script {
+    async fn up(&self, _env: Env) -> Result<()> {
+        Ok(())
+    }
+
+    fn script(&self) -> Option<String> {
+        Some(self.script.to_string())
+    }
+}
+
+pub async fn changed_applied_migration_is_reported_according_to_policy(t: &TestDb) {
+    let migrations: Vec<Box<dyn Migration>> = vec![Box::new(Script {
+        script: "db.getCollection('users').insertOne({name: 'Batman'});",
+    })];
+    init_migrator_with_migrations(t.db.clone(), migrations)
+        .up()
+        .await
+        .unwrap();
+
+    let migration_record =
+        t.db.collection::<MigrationRecord>("migrations")
+            .find_one(bson::doc! {"_id": "Script"})
+            .await
+            .unwrap()
+            .unwrap();
+    assert_eq!(
+        migration_record.checksum,
+        Some(calc_checksum(
+            "db.getCollection('users').insertOne({name: 'Batman'});"
+        ))
+    );
+
+    let migrations: Vec<Box<dyn Migration>> = vec![Box::new(Script {
+        script: "db.getCollection('users').insertOne({name: 'Superman'});",
+    })];
+    let mut migrator = init_migrator_with_migrations(t.db.clone(), migrations);
+
+    assert!(migrator.up().await.is_ok());
+
+    match migrator
+        .set_checksum_policy(ChecksumPolicy::Error)
+        .up()
+        .await
+    {
+        Err(MigrationExecution::ChecksumsMismatch { mismatches }) => {
+            assert_eq!(mismatches.len(), 1);
+            assert_eq!(mismatches[0].migration_id, "Script");
+            assert_eq!(mismatches[0].stored, migration_record.checksum.unwrap());
+        }
+        _ => unreachable!(),
+    }
+}
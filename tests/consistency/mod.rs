@@ -0,0 +1,84 @@
+//! These tests check that records of unknown migrations and migrations
+//! inserted before applied ones are reported according to policies
+use mongodb_migrator::{
+    error::MigrationExecution,
+    migration::Migration,
+    migrator::consistency::{
+        ConsistencyConfig, ConsistencyPolicy, ConsistencyReport, OutOfOrderMigration,
+    },
+};
+
+use super::utils::{init_migrator_with_migrations, TestDb, M0, M1, M2};
+
+pub async fn unknown_records_are_reported_according_to_policy(t: &TestDb) {
+    let migrations: Vec<Box<dyn Migration>> =
+        vec![Box::new(M0 {}), Box::new(M1 {}), Box::new(M2 {})];
+    init_migrator_with_migrations(t.db.clone(), migrations)
+        .up()
+        .await
+        .unwrap();
+
+    // M1 is deleted from the code
+    let migrations: Vec<Box<dyn Migration>> = vec![Box::new(M0 {}), Box::new(M2 {})];
+    let mut migrator = init_migrator_with_migrations(t.db.clone(), migrations);
+
+    assert_eq!(
+        migrator.check_consistency().await.unwrap(),
+        ConsistencyReport {
+            unknown_records: vec![M1 {}.get_id().to_string()],
+            out_of_order: vec![],
+        }
+    );
+    // warned by default
+    migrator.up().await.unwrap();
+
+    migrator.set_consistency_config(ConsistencyConfig {
+        unknown_records: ConsistencyPolicy::Error,
+        ..Default::default()
+    });
+    match migrator.up().await {
+        Err(MigrationExecution::UnknownMigrationRecords { migrations_ids }) => {
+            assert_eq!(migrations_ids, vec![M1 {}.get_id().to_string()])
+        }
+        res => panic!("unexpected result: {res:?}"),
+    }
+}
+
+pub async fn migration_inserted_before_applied_ones_is_reported_according_to_policy(t: &TestDb) {
+    let migrations: Vec<Box<dyn Migration>> = vec![Box::new(M0 {}), Box::new(M2 {})];
+    init_migrator_with_migrations(t.db.clone(), migrations)
+        .up()
+        .await
+        .unwrap();
+
+    // M1 is added between applied migrations
+    let migrations: Vec<Box<dyn Migration>> =
+        vec![Box::new(M0 {}), Box::new(M1 {}), Box::new(M2 {})];
+    let mut migrator = init_migrator_with_migrations(t.db.clone(), migrations);
+    migrator.set_consistency_config(ConsistencyConfig {
+        out_of_order: ConsistencyPolicy::Error,
+        ..Default::default()
+    });
+
+    let out_of_order = vec![OutOfOrderMigration {
+        migration_id: M1 {}.get_id().to_string(),
+        applied_after: vec![M2 {}.get_id().to_string()],
+    }];
+    let report = migrator.check_consistency().await.unwrap();
+    assert!(!report.is_consistent());
+    assert_eq!(report.out_of_order, out_of_order);
+
+    match migrator.up().await {
+        Err(MigrationExecution::OutOfOrderMigrations { out_of_order: res }) => {
+            assert_eq!(res, out_of_order)
+        }
+        res => panic!("unexpected result: {res:?}"),
+    }
+
+    migrator.set_consistency_config(ConsistencyConfig {
+        out_of_order: ConsistencyPolicy::Allow,
+        ..Default::default()
+    });
+    migrator.up().await.unwrap();
+    assert!(migrator.check_consistency().await.unwrap().is_consistent());
+}
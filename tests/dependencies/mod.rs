@@ -0,0 +1,136 @@
+//! These tests check that migrations are ordered by their declared dependencies
+use anyhow::Result;
+use async_trait::async_trait;
+use bson::Bson;
+use futures::stream::StreamExt;
+use mongodb_migrator::{
+    error::MigrationExecution, migration::Migration, migration_record::MigrationRecord,
+    migrator::Env,
+};
+
+use super::utils::{init_migrator_with_migrations, TestDb};
+
+struct A {}
+struct B {}
+struct C {}
+struct Cyclic0 {}
+struct Cyclic1 {}
+
+#[async_trait]
+impl Migration for A {
+    async fn up(&self, _env: Env) -> Result<()> {
+        Ok(())
+    }
+
+    fn depends_on(&self) -> Vec<String> {
+        vec![B {}.get_id().to_string()]
+    }
+}
+
+#[async_trait]
+impl Migration for B {
+    async fn up(&self, _env: Env) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Migration for C {
+    async fn up(&self, _env: Env) -> Result<()> {
+        Ok(())
+    }
+
+    fn depends_on(&self) -> Vec<String> {
+        vec!["NotPassed".to_string()]
+    }
+}
+
+#[async_trait]
+impl Migration for Cyclic0 {
+    async fn up(&self, _env: Env) -> Result<()> {
+        Ok(())
+    }
+
+    fn depends_on(&self) -> Vec<String> {
+        vec![Cyclic1 {}.get_id().to_string()]
+    }
+}
+
+#[async_trait]
+impl Migration for Cyclic1 {
+    async fn up(&self, _env: Env) -> Result<()> {
+        Ok(())
+    }
+
+    fn depends_on(&self) -> Vec<String> {
+        vec![Cyclic0 {}.get_id().to_string()]
+    }
+}
+
+// B -> A
+pub async fn migrations_executed_after_their_dependencies(t: &TestDb) {
+    let migrations: Vec<Box<dyn Migration>> = vec![Box::new(A {}), Box::new(B {})];
+
+    init_migrator_with_migrations(t.db.clone(), migrations)
+        .up()
+        .await
+        .unwrap();
+
+    let all_records =
+        t.db.collection("migrations")
+            .find(bson::doc! {})
+            .sort(bson::doc! {"end_date": 1})
+            .await
+            .unwrap()
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .map(|v| bson::from_bson(Bson::Document(v.unwrap())).unwrap())
+            .map(|v: MigrationRecord| v._id)
+            .collect::<Vec<String>>();
+
+    assert_eq!(
+        all_records,
+        vec![B {}.get_id().to_string(), A {}.get_id().to_string()]
+    );
+}
+
+pub async fn validation_fails_when_dependency_is_missing(t: &TestDb) {
+    let migrations: Vec<Box<dyn Migration>> = vec![Box::new(B {}), Box::new(C {})];
+
+    let res = init_migrator_with_migrations(t.db.clone(), migrations)
+        .up()
+        .await;
+
+    match res {
+        Err(MigrationExecution::MissingDependencies { missing }) => {
+            assert_eq!(
+                missing.get(C {}.get_id()),
+                Some(&vec!["NotPassed".to_string()])
+            );
+        }
+        _ => unreachable!(),
+    }
+}
+
+pub async fn validation_fails_when_dependencies_form_cycle(t: &TestDb) {
+    let migrations: Vec<Box<dyn Migration>> =
+        vec![Box::new(B {}), Box::new(Cyclic0 {}), Box::new(Cyclic1 {})];
+
+    let res = init_migrator_with_migrations(t.db.clone(), migrations)
+        .up()
+        .await;
+
+    match res {
+        Err(MigrationExecution::DependenciesCycle { cycle }) => {
+            assert_eq!(
+                cycle,
+                vec![
+                    Cyclic0 {}.get_id().to_string(),
+                    Cyclic1 {}.get_id().to_string()
+                ]
+            );
+        }
+        _ => unreachable!(),
+    }
+}
@@ -0,0 +1,139 @@
+//! These tests check what migrations get from the migrator via [`Env`]
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use mongodb_migrator::{
+    migration::Migration,
+    migrator::{env::Progress, Env},
+};
+use tokio_util::sync::CancellationToken;
+
+use super::utils::{init_migrator_with_migrations, TestDb};
+
+struct AppConfig {
+    users_collection: String,
+}
+
+/// Saves what it has got from the env so that a test can check it
+struct InspectsEnv {
+    seen: Arc<Mutex<Option<(String, String)>>>,
+}
+
+#[async_trait]
+impl Migration for InspectsEnv {
+    async fn up(&self, env: Env) -> Result<()> {
+        let config = env
+            .extensions
+            .get::<AppConfig>()
+            .expect("app config is passed");
+
+        env.client
+            .expect("client is available")
+            .database("other")
+            .collection(&config.users_collection)
+            .insert_one(bson::doc! {"x": 0})
+            .await?;
+
+        env.progress.report(1, Some(2), None);
+        env.progress
+            .report(2, Some(2), Some("users are migrated".to_string()));
+
+        *self.seen.lock().unwrap() = Some((env.migration_id, env.run_id));
+
+        Ok(())
+    }
+}
+
+/// Hangs until its timeout and lets a spawned task observe the cancellation
+struct WaitsForCancellation {
+    cancellation: Arc<Mutex<Option<CancellationToken>>>,
+}
+
+#[async_trait]
+impl Migration for WaitsForCancellation {
+    async fn up(&self, env: Env) -> Result<()> {
+        *self.cancellation.lock().unwrap() = Some(env.cancellation.clone());
+        tokio::time::sleep(Duration::from_secs(3600)).await;
+
+        Ok(())
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        Some(Duration::from_millis(100))
+    }
+}
+
+pub async fn env_contains_ids_client_progress_and_extensions(t: &TestDb) {
+    let seen = Arc::new(Mutex::new(None));
+    let reported = Arc::new(Mutex::new(vec![]));
+    let migrations: Vec<Box<dyn Migration>> = vec![Box::new(InspectsEnv { seen: seen.clone() })];
+
+    let mut migrator = init_migrator_with_migrations(t.db.clone(), migrations);
+    let reported_by_handler = reported.clone();
+    migrator
+        .set_extension(AppConfig {
+            users_collection: "users".to_string(),
+        })
+        .set_progress_handler(move |progress: &Progress| {
+            reported_by_handler.lock().unwrap().push(progress.clone())
+        });
+
+    migrator.up().await.unwrap();
+
+    let run_id = migrator.get_last_run_id().await.unwrap().unwrap();
+    let migration_id = InspectsEnv { seen: seen.clone() }.get_id().to_string();
+    assert_eq!(
+        seen.lock().unwrap().clone(),
+        Some((migration_id.clone(), run_id.clone()))
+    );
+    assert_eq!(
+        reported
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|progress| (
+                progress.migration_id.as_str(),
+                progress.run_id.as_str(),
+                progress.done
+            ))
+            .collect::<Vec<_>>(),
+        vec![
+            (migration_id.as_str(), run_id.as_str(), 1),
+            (migration_id.as_str(), run_id.as_str(), 2)
+        ]
+    );
+    assert_eq!(
+        t.db.client()
+            .database("other")
+            .collection::<bson::Document>("users")
+            .count_documents(bson::doc! {})
+            .await
+            .unwrap(),
+        1
+    );
+
+    t.db.client().database("other").drop().await.unwrap();
+}
+
+pub async fn cancellation_is_signalled_when_timeout_is_elapsed(t: &TestDb) {
+    let cancellation = Arc::new(Mutex::new(None));
+    let migrations: Vec<Box<dyn Migration>> = vec![Box::new(WaitsForCancellation {
+        cancellation: cancellation.clone(),
+    })];
+
+    let res = init_migrator_with_migrations(t.db.clone(), migrations)
+        .up()
+        .await;
+
+    assert!(res.is_err());
+    assert!(cancellation
+        .lock()
+        .unwrap()
+        .as_ref()
+        .expect("migration was started")
+        .is_cancelled());
+}
@@ -0,0 +1,174 @@
+//! These tests check that the migrations state is exported to JSON
+//! and imported into another database according to the conflict policy
+use mongodb_migrator::{
+    error::MigrationExecution,
+    migration::Migration,
+    migration_record::MigrationRecord,
+    migration_status::MigrationStatus,
+    migrator::{
+        export::{ConflictPolicy, ExportFormat, StateExport},
+        store::MemoryMigrationStore,
+        DefaultMigrator, Migrator,
+    },
+    state_metadata::STATE_FORMAT_VERSION,
+};
+
+use super::utils::{init_migrator_with_migrations, TestDb, M0, M1, M2};
+
+pub async fn state_is_exported_and_imported_into_another_db(t: &TestDb) {
+    let migrations: Vec<Box<dyn Migration>> = vec![Box::new(M0 {}), Box::new(M1 {})];
+    let migrator = init_migrator_with_migrations(t.db.clone(), migrations);
+    migrator.up().await.unwrap();
+
+    let json = migrator
+        .export_state()
+        .await
+        .unwrap()
+        .to_json(ExportFormat::ExtendedJson)
+        .unwrap();
+
+    let other_db = t.db.client().database("imported");
+    let migrations: Vec<Box<dyn Migration>> =
+        vec![Box::new(M0 {}), Box::new(M1 {}), Box::new(M2 {})];
+    let other_migrator = DefaultMigrator::new()
+        .with_conn(other_db.clone())
+        .with_migrations_vec(migrations);
+
+    let import_report = other_migrator
+        .import_state(StateExport::from_json(&json).unwrap(), ConflictPolicy::Fail)
+        .await
+        .unwrap();
+    assert_eq!(
+        import_report.imported,
+        vec![M0 {}.get_id().to_string(), M1 {}.get_id().to_string()]
+    );
+    assert_eq!(import_report.history_imported, 2);
+
+    let migration_record = other_db
+        .collection::<MigrationRecord>("migrations")
+        .find_one(bson::doc! {"_id": M0 {}.get_id()})
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(migration_record.status, MigrationStatus::Success);
+    assert_eq!(migration_record.target.as_deref(), Some(other_db.name()));
+
+    // imported migrations aren't executed again, only the new one is
+    other_migrator.up().await.unwrap();
+    assert_eq!(
+        other_db
+            .collection::<bson::Document>("users")
+            .count_documents(bson::doc! {})
+            .await
+            .unwrap(),
+        0
+    );
+    assert_eq!(
+        other_migrator
+            .get_history_by_migration_id(M2 {}.get_id())
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+
+    match other_migrator
+        .import_state(StateExport::from_json(&json).unwrap(), ConflictPolicy::Fail)
+        .await
+    {
+        Err(MigrationExecution::ImportConflicts { migrations_ids }) => assert_eq!(
+            migrations_ids,
+            vec![M0 {}.get_id().to_string(), M1 {}.get_id().to_string()]
+        ),
+        res => panic!("unexpected result: {res:?}"),
+    }
+
+    other_db.drop().await.unwrap();
+}
+
+#[tokio::test]
+async fn export_is_read_back_from_both_formats() {
+    let store = MemoryMigrationStore::new();
+    store.insert_record(
+        MigrationRecord::migration_start(M0 {}.get_id().to_string()).migration_succeeded(),
+    );
+    let migrations: Vec<Box<dyn Migration>> = vec![Box::new(M0 {})];
+    let migrator = Migrator::builder()
+        .with_memory_store(store)
+        .build(migrations);
+
+    let state_export = migrator.export_state().await.unwrap();
+    assert_eq!(state_export.format_version, STATE_FORMAT_VERSION);
+    assert_eq!(state_export.records.len(), 1);
+
+    for format in [ExportFormat::Json, ExportFormat::ExtendedJson] {
+        let json = state_export.to_json(format).unwrap();
+        assert_eq!(StateExport::from_json(&json).unwrap(), state_export);
+    }
+
+    assert!(matches!(
+        StateExport::from_json("{\"records\": 1}"),
+        Err(MigrationExecution::InvalidStateExport { .. })
+    ));
+}
+
+#[tokio::test]
+async fn conflicting_records_are_handled_by_policy() {
+    let source = MemoryMigrationStore::new();
+    source.insert_record(
+        MigrationRecord::migration_start(M0 {}.get_id().to_string()).migration_succeeded(),
+    );
+    source.insert_record(
+        MigrationRecord::migration_start(M1 {}.get_id().to_string()).migration_succeeded(),
+    );
+    let state_export = Migrator::builder()
+        .with_memory_store(source)
+        .build(vec![])
+        .export_state()
+        .await
+        .unwrap();
+
+    let store = MemoryMigrationStore::new();
+    store.insert_record(
+        MigrationRecord::migration_start(M0 {}.get_id().to_string()).migration_failed(),
+    );
+    let migrator = Migrator::builder()
+        .with_memory_store(store.clone())
+        .build(vec![]);
+
+    // nothing is imported when at least one record conflicts
+    match migrator
+        .import_state(state_export.clone(), ConflictPolicy::Fail)
+        .await
+    {
+        Err(MigrationExecution::ImportConflicts { migrations_ids }) => {
+            assert_eq!(migrations_ids, vec![M0 {}.get_id().to_string()])
+        }
+        res => panic!("unexpected result: {res:?}"),
+    }
+    assert_eq!(store.records().len(), 1);
+
+    let import_report = migrator
+        .import_state(state_export.clone(), ConflictPolicy::Skip)
+        .await
+        .unwrap();
+    assert_eq!(import_report.skipped, vec![M0 {}.get_id().to_string()]);
+    assert_eq!(import_report.imported, vec![M1 {}.get_id().to_string()]);
+    assert_eq!(
+        store.records()[M0 {}.get_id()].status,
+        MigrationStatus::Fail
+    );
+
+    let import_report = migrator
+        .import_state(state_export, ConflictPolicy::Overwrite)
+        .await
+        .unwrap();
+    assert_eq!(
+        import_report.overwritten,
+        vec![M0 {}.get_id().to_string(), M1 {}.get_id().to_string()]
+    );
+    assert_eq!(
+        store.records()[M0 {}.get_id()].status,
+        MigrationStatus::Success
+    );
+}
@@ -2,7 +2,8 @@
 use bson::{self, Bson};
 use futures::stream::StreamExt;
 use mongodb_migrator::{
-    migration::Migration, migration_record::MigrationRecord, migration_status::MigrationStatus,
+    error::MigrationExecution, migration::Migration, migration_record::MigrationRecord,
+    migration_status::MigrationStatus,
 };
 
 use super::utils::{init_migrator_with_migrations, TestDb, Users, M0, M1, M2, M3};
@@ -57,3 +58,37 @@ pub async fn with_failed_migration_should_stop_after_first_fail_and_save_failed_
         4
     );
 }
+
+pub async fn failed_migration_keeps_its_error_in_record_and_result(t: &TestDb) {
+    let migrations: Vec<Box<dyn Migration>> = vec![Box::new(M0 {}), Box::new(M3 {})];
+
+    let res = init_migrator_with_migrations(t.db.clone(), migrations)
+        .up()
+        .await;
+
+    match res {
+        Err(MigrationExecution::FinishedAndSavedAsFail {
+            migration_id,
+            error,
+            ..
+        }) => {
+            assert_eq!(migration_id, M3 {}.get_id());
+            assert_eq!(error.to_string(), "test error");
+        }
+        _ => unreachable!(),
+    }
+
+    let failed_migration: MigrationRecord = bson::from_bson(Bson::Document(
+        t.db.collection("migrations")
+            .find_one(bson::doc! {"_id": M3 {}.get_id()})
+            .await
+            .unwrap()
+            .unwrap(),
+    ))
+    .unwrap();
+
+    assert_eq!(failed_migration.status, MigrationStatus::Fail);
+    let error = failed_migration.error.expect("error is saved");
+    assert_eq!(error.message, "test error");
+    assert_eq!(error.chain, vec!["test error".to_string()]);
+}
@@ -0,0 +1,53 @@
+//! These tests check that every up/down attempt is kept in the history collection
+use mongodb_migrator::{
+    migration::Migration, migration_status::MigrationStatus, operation_type::OperationType,
+};
+
+use super::utils::{init_migrator_with_migrations, TestDb, M0, M1, M3};
+
+pub async fn every_attempt_is_appended_to_history(t: &TestDb) {
+    let migrations: Vec<Box<dyn Migration>> = vec![Box::new(M0 {}), Box::new(M1 {})];
+    let migrator = init_migrator_with_migrations(t.db.clone(), migrations);
+
+    migrator.up().await.unwrap();
+
+    let m0_history = migrator
+        .get_history_by_migration_id(M0 {}.get_id())
+        .await
+        .unwrap();
+    let m1_history = migrator
+        .get_history_by_migration_id(M1 {}.get_id())
+        .await
+        .unwrap();
+
+    assert_eq!(m0_history.len(), 1);
+    assert_eq!(m1_history.len(), 1);
+    assert_eq!(m0_history[0].direction, OperationType::Up);
+    assert_eq!(m0_history[0].status, MigrationStatus::Success);
+    assert_eq!(m0_history[0].attempt, 1);
+    assert!(m0_history[0].end_date.is_some());
+    assert_eq!(m0_history[0].run_id, m1_history[0].run_id);
+}
+
+pub async fn run_history_contains_failed_attempt_with_error(t: &TestDb) {
+    let migrations: Vec<Box<dyn Migration>> = vec![Box::new(M0 {}), Box::new(M3 {})];
+    let migrator = init_migrator_with_migrations(t.db.clone(), migrations);
+
+    let _ = migrator.up().await;
+
+    let run_id = migrator.get_last_run_id().await.unwrap().unwrap();
+    let history = migrator.get_history_by_run_id(run_id).await.unwrap();
+
+    assert_eq!(
+        history
+            .iter()
+            .map(|v| v.migration_id.clone())
+            .collect::<Vec<String>>(),
+        vec![M0 {}.get_id().to_string(), M3 {}.get_id().to_string()]
+    );
+    assert_eq!(history[1].status, MigrationStatus::Fail);
+    assert_eq!(
+        history[1].error.as_ref().expect("error is saved").message,
+        "test error"
+    );
+}
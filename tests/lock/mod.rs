@@ -0,0 +1,76 @@
+//! These tests check that the migrations lock doesn't let concurrent migrators run the same migrations
+use std::time::Duration;
+
+use mongodb_migrator::{
+    error::MigrationExecution,
+    migration::Migration,
+    migrator::lock::{LockConfig, LockWait, MigrationLock},
+};
+
+use super::utils::{init_migrator_with_migrations, TestDb, Users, M0};
+
+pub async fn concurrent_migrators_execute_migration_once(t: &TestDb) {
+    let mut first = init_migrator_with_migrations(t.db.clone(), vec![Box::new(M0 {})]);
+    first.set_lock_config(LockConfig {
+        owner: "first".to_string(),
+        ..Default::default()
+    });
+    let mut second = init_migrator_with_migrations(t.db.clone(), vec![Box::new(M0 {})]);
+    second.set_lock_config(LockConfig {
+        owner: "second".to_string(),
+        wait: LockWait::WaitWithTimeout {
+            timeout: Duration::from_secs(30),
+            poll_interval: Duration::from_millis(50),
+        },
+        ..Default::default()
+    });
+
+    let (first_res, second_res) = tokio::join!(first.up(), second.up());
+
+    assert!(first_res.is_ok());
+    assert!(second_res.is_ok());
+    assert_eq!(
+        t.db.collection::<Users>("users")
+            .count_documents(bson::doc! {})
+            .await
+            .unwrap(),
+        1
+    );
+    assert!(first.lock_status().await.unwrap().is_none());
+}
+
+pub async fn fail_fast_when_lock_is_held_and_force_unlock_releases_it(t: &TestDb) {
+    let now = bson::DateTime::now();
+    let held_lock = MigrationLock {
+        _id: "migrations_lock".to_string(),
+        owner: "someone else".to_string(),
+        acquired_at: now,
+        heartbeat_at: now,
+        expires_at: bson::DateTime::from_millis(now.timestamp_millis() + 3_600_000),
+    };
+    t.db.collection::<MigrationLock>("migrations_lock")
+        .insert_one(&held_lock)
+        .await
+        .unwrap();
+
+    let migrations: Vec<Box<dyn Migration>> = vec![Box::new(M0 {})];
+    let mut migrator = init_migrator_with_migrations(t.db.clone(), migrations);
+    migrator.set_lock_config(LockConfig {
+        wait: LockWait::FailFast,
+        ..Default::default()
+    });
+
+    match migrator.up().await {
+        Err(MigrationExecution::LockNotAcquired { held_by, .. }) => {
+            assert_eq!(held_by, Some(held_lock.clone()));
+        }
+        _ => unreachable!(),
+    }
+    assert_eq!(
+        migrator.lock_status().await.unwrap(),
+        Some(held_lock.clone())
+    );
+
+    assert_eq!(migrator.force_unlock().await.unwrap(), Some(held_lock));
+    assert!(migrator.up().await.is_ok());
+}
@@ -0,0 +1,105 @@
+//! These tests check that migrations state can be changed by hand without executing migrations
+use mongodb_migrator::{
+    migration::Migration,
+    migration_status::MigrationStatus,
+    migrator::plan::{PlanDecision, RunReason, SkipReason},
+    operation_type::{ManualOperation, OperationType},
+};
+
+use super::utils::{get_record, init_migrator_with_migrations, TestDb, M0, M1, M2};
+
+async fn count_users(t: &TestDb) -> u64 {
+    t.db.collection::<bson::Document>("users")
+        .count_documents(bson::doc! {})
+        .await
+        .unwrap()
+}
+
+pub async fn baseline_marks_migrations_as_applied_without_executing(t: &TestDb) {
+    let migrations: Vec<Box<dyn Migration>> =
+        vec![Box::new(M0 {}), Box::new(M1 {}), Box::new(M2 {})];
+    let migrator = init_migrator_with_migrations(t.db.clone(), migrations);
+
+    assert_eq!(
+        migrator
+            .baseline_up_to(M1 {}.get_id().to_string())
+            .await
+            .unwrap(),
+        vec![M0 {}.get_id().to_string(), M1 {}.get_id().to_string()]
+    );
+    assert_eq!(count_users(t).await, 0);
+
+    for migration_id in [M0 {}.get_id(), M1 {}.get_id()] {
+        let migration_record = get_record(t, migration_id).await.unwrap();
+        assert_eq!(migration_record.status, MigrationStatus::Success);
+        assert_eq!(migration_record.manual, Some(ManualOperation::Baseline));
+        assert_eq!(migration_record.attempts, 0);
+
+        let history = migrator
+            .get_history_by_migration_id(migration_id)
+            .await
+            .unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].manual, Some(ManualOperation::Baseline));
+    }
+    assert!(get_record(t, M2 {}.get_id()).await.is_none());
+
+    // the baseline is already applied, so it's skipped
+    assert!(migrator
+        .baseline_up_to(M0 {}.get_id().to_string())
+        .await
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        migrator.plan(OperationType::Up).await.unwrap().ids_to_run(),
+        vec![M2 {}.get_id().to_string()]
+    );
+}
+
+pub async fn marked_and_forgotten_migrations_are_executed_by_next_up(t: &TestDb) {
+    let migrations: Vec<Box<dyn Migration>> =
+        vec![Box::new(M0 {}), Box::new(M1 {}), Box::new(M2 {})];
+    let migrator = init_migrator_with_migrations(t.db.clone(), migrations);
+    migrator.up().await.unwrap();
+
+    migrator
+        .mark_failed(M1 {}.get_id().to_string())
+        .await
+        .unwrap();
+    migrator.forget(M2 {}.get_id().to_string()).await.unwrap();
+
+    let migration_record = get_record(t, M1 {}.get_id()).await.unwrap();
+    assert_eq!(migration_record.status, MigrationStatus::Fail);
+    assert_eq!(migration_record.manual, Some(ManualOperation::MarkFailed));
+    assert_eq!(migration_record.attempts, 1);
+    assert!(get_record(t, M2 {}.get_id()).await.is_none());
+    let history = migrator
+        .get_history_by_migration_id(M2 {}.get_id())
+        .await
+        .unwrap();
+    assert_eq!(
+        history.last().unwrap().manual,
+        Some(ManualOperation::Forget)
+    );
+
+    let plan = migrator.plan(OperationType::Up).await.unwrap();
+    assert_eq!(
+        plan.entries
+            .into_iter()
+            .map(|entry| entry.decision)
+            .collect::<Vec<_>>(),
+        vec![
+            PlanDecision::Skip(SkipReason::AlreadySucceeded),
+            PlanDecision::Run(RunReason::PreviouslyFailed),
+            PlanDecision::Run(RunReason::NeverRun),
+        ]
+    );
+
+    migrator
+        .mark_applied(M2 {}.get_id().to_string())
+        .await
+        .unwrap();
+    let migration_record = get_record(t, M2 {}.get_id()).await.unwrap();
+    assert_eq!(migration_record.status, MigrationStatus::Success);
+    assert_eq!(migration_record.manual, Some(ManualOperation::MarkApplied));
+}
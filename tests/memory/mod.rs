@@ -0,0 +1,359 @@
+//! These tests execute stub migrations with the in-memory store, so they don't need Docker
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use mongodb_migrator::{
+    error::MigrationExecution,
+    migration::Migration,
+    migration_status::MigrationStatus,
+    migrator::{execution_strategy::ExecutionStrategy, store::MemoryMigrationStore, Env, Migrator},
+};
+
+use super::observer::Recorder;
+
+/// Ids of executed migrations in the execution order
+#[derive(Default)]
+struct Calls {
+    executed: Mutex<Vec<String>>,
+    /// How many times a migration, or only its `up`/`down` like "down a", has to fail before it succeeds
+    failures_left: Mutex<BTreeMap<String, usize>>,
+}
+
+impl Calls {
+    fn failing(failures: &[(&str, usize)]) -> Self {
+        Self {
+            failures_left: Mutex::new(
+                failures
+                    .iter()
+                    .map(|(id, count)| (id.to_string(), *count))
+                    .collect(),
+            ),
+            ..Default::default()
+        }
+    }
+
+    fn executed(&self) -> Vec<String> {
+        self.executed.lock().unwrap().clone()
+    }
+}
+
+struct Stub {
+    id: &'static str,
+    depends_on: Vec<&'static str>,
+}
+
+impl Stub {
+    fn boxed(id: &'static str, depends_on: Vec<&'static str>) -> Box<dyn Migration> {
+        Box::new(Self { id, depends_on })
+    }
+
+    fn call(&self, env: &Env, direction: &str) -> Result<()> {
+        let calls = env
+            .extensions
+            .get::<Arc<Calls>>()
+            .expect("calls are passed");
+        let call = format!("{direction} {}", self.id);
+        calls.executed.lock().unwrap().push(call.clone());
+
+        let mut failures_left = calls.failures_left.lock().unwrap();
+        let key = if failures_left.contains_key(&call) {
+            call
+        } else {
+            self.id.to_string()
+        };
+        match failures_left.get_mut(&key) {
+            Some(failures_left) if *failures_left > 0 => {
+                *failures_left -= 1;
+                Err(anyhow::anyhow!("stub error"))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl Migration for Stub {
+    async fn up(&self, env: Env) -> Result<()> {
+        assert!(env.db.is_none());
+        self.call(&env, "up")
+    }
+
+    async fn down(&self, env: Env) -> Result<()> {
+        self.call(&env, "down")
+    }
+
+    fn depends_on(&self) -> Vec<String> {
+        self.depends_on.iter().map(|id| id.to_string()).collect()
+    }
+
+    fn get_id(&self) -> &str {
+        self.id
+    }
+}
+
+fn statuses(store: &MemoryMigrationStore) -> Vec<(String, MigrationStatus)> {
+    store
+        .records()
+        .into_values()
+        .map(|migration_record| (migration_record._id, migration_record.status))
+        .collect()
+}
+
+#[tokio::test]
+async fn migrations_are_ordered_by_dependencies_and_rolled_back_in_reverse() {
+    let store = MemoryMigrationStore::new();
+    let calls = Arc::new(Calls::default());
+    let migrator = Migrator::builder()
+        .with_memory_store(store.clone())
+        .with_extension(calls.clone())
+        .build(vec![
+            Stub::boxed("a", vec!["b"]),
+            Stub::boxed("b", vec![]),
+            Stub::boxed("c", vec![]),
+        ]);
+
+    migrator.up().await.unwrap();
+    migrator.down_last(2).await.unwrap();
+
+    assert_eq!(
+        calls.executed(),
+        vec!["up b", "up a", "up c", "down c", "down a"]
+    );
+    assert_eq!(
+        statuses(&store),
+        vec![
+            ("a".to_string(), MigrationStatus::RolledBack),
+            ("b".to_string(), MigrationStatus::Success),
+            ("c".to_string(), MigrationStatus::RolledBack),
+        ]
+    );
+    assert_eq!(store.history().len(), 5);
+}
+
+#[tokio::test]
+async fn failed_migration_stops_next_ones_and_is_rerun_by_next_up() {
+    let store = MemoryMigrationStore::new();
+    let calls = Arc::new(Calls::failing(&[("b", 1)]));
+    let migrator = Migrator::builder()
+        .with_memory_store(store.clone())
+        .with_extension(calls.clone())
+        .build(vec![
+            Stub::boxed("a", vec![]),
+            Stub::boxed("b", vec![]),
+            Stub::boxed("c", vec![]),
+        ]);
+
+    match migrator.up().await {
+        Err(MigrationExecution::FinishedAndSavedAsFail {
+            migration_id,
+            next_not_executed_migrations_ids,
+            ..
+        }) => {
+            assert_eq!(migration_id, "b");
+            assert_eq!(next_not_executed_migrations_ids, vec!["c".to_string()]);
+        }
+        res => panic!("unexpected result: {res:?}"),
+    }
+    assert_eq!(
+        statuses(&store),
+        vec![
+            ("a".to_string(), MigrationStatus::Success),
+            ("b".to_string(), MigrationStatus::Fail),
+            ("c".to_string(), MigrationStatus::Fail),
+        ]
+    );
+
+    migrator.up().await.unwrap();
+
+    assert_eq!(calls.executed(), vec!["up a", "up b", "up b", "up c"]);
+    assert!(statuses(&store)
+        .into_iter()
+        .all(|(_, status)| status == MigrationStatus::Success));
+    assert_eq!(store.records()["b"].attempts, 2);
+}
+
+#[tokio::test]
+async fn failed_attempts_are_retried_within_the_same_run() {
+    let store = MemoryMigrationStore::new();
+    let calls = Arc::new(Calls::failing(&[("a", 2)]));
+    let migrator = Migrator::builder()
+        .with_memory_store(store.clone())
+        .with_extension(calls.clone())
+        .with_retries(2, Duration::ZERO)
+        .build(vec![Stub::boxed("a", vec![])]);
+
+    migrator.up().await.unwrap();
+
+    assert_eq!(calls.executed(), vec!["up a", "up a", "up a"]);
+    assert_eq!(store.records()["a"].status, MigrationStatus::Success);
+
+    let run_id = migrator.get_last_run_id().await.unwrap().unwrap();
+    let history = migrator.get_history_by_run_id(&run_id).await.unwrap();
+    assert_eq!(
+        history
+            .iter()
+            .map(|history_record| (history_record.attempt, history_record.status.clone()))
+            .collect::<Vec<_>>(),
+        vec![
+            (1, MigrationStatus::Fail),
+            (2, MigrationStatus::Fail),
+            (3, MigrationStatus::Success),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn try_all_doesnt_execute_migrations_blocked_by_failed_ones() {
+    let store = MemoryMigrationStore::new();
+    let calls = Arc::new(Calls::failing(&[("a", 1)]));
+    let recorder = Recorder::default();
+    let migrator = Migrator::builder()
+        .with_memory_store(store.clone())
+        .with_extension(calls.clone())
+        .with_observer(recorder.clone())
+        .with_execution_strategy(ExecutionStrategy::TryAll)
+        .build(vec![
+            Stub::boxed("a", vec![]),
+            Stub::boxed("b", vec!["a"]),
+            Stub::boxed("c", vec!["b"]),
+            Stub::boxed("d", vec![]),
+        ]);
+
+    match migrator.up().await {
+        Err(MigrationExecution::TryAllFinishedWithFailures { failures }) => {
+            assert_eq!(
+                failures
+                    .iter()
+                    .map(|(migration_id, _)| migration_id.as_str())
+                    .collect::<Vec<_>>(),
+                vec!["a", "b", "c"]
+            );
+            assert!(matches!(
+                &failures[2].1,
+                MigrationExecution::DependencyFailed { failed, .. } if failed == &vec!["b".to_string()]
+            ));
+        }
+        res => panic!("unexpected result: {res:?}"),
+    }
+
+    assert_eq!(calls.executed(), vec!["up a", "up d"]);
+    assert_eq!(
+        statuses(&store),
+        vec![
+            ("a".to_string(), MigrationStatus::Fail),
+            ("b".to_string(), MigrationStatus::Fail),
+            ("c".to_string(), MigrationStatus::Fail),
+            ("d".to_string(), MigrationStatus::Success),
+        ]
+    );
+    assert_eq!(
+        *recorder.events.lock().unwrap(),
+        vec![
+            "before_all [\"a\", \"b\", \"c\", \"d\"]",
+            "before_each a",
+            "after_each a Fail",
+            "on_failure a",
+            "before_each b",
+            "after_each b Fail",
+            "on_failure b",
+            "before_each c",
+            "after_each c Fail",
+            "on_failure c",
+            "before_each d",
+            "after_each d Success",
+            "after_all false",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn migrator_without_store_and_connection_returns_error() {
+    let mut migrator = Migrator::builder()
+        .with_memory_store(MemoryMigrationStore::new())
+        .build(vec![Stub::boxed("a", vec![])]);
+    migrator.store = None;
+
+    assert!(matches!(
+        migrator.up().await,
+        Err(MigrationExecution::StoreNotSet)
+    ));
+}
+
+#[tokio::test]
+async fn migration_stays_applied_when_its_rollback_fails() {
+    let store = MemoryMigrationStore::new();
+    let calls = Arc::new(Calls::failing(&[("down b", 1)]));
+    let migrator = Migrator::builder()
+        .with_memory_store(store.clone())
+        .with_extension(calls.clone())
+        .build(vec![Stub::boxed("a", vec![]), Stub::boxed("b", vec![])]);
+
+    migrator.up().await.unwrap();
+    assert!(migrator.down().await.is_err());
+
+    assert_eq!(
+        statuses(&store),
+        vec![
+            ("a".to_string(), MigrationStatus::Success),
+            ("b".to_string(), MigrationStatus::RollbackFailed),
+        ]
+    );
+    assert!(store.records()["b"].error.is_some());
+
+    migrator.up().await.unwrap();
+    migrator.down().await.unwrap();
+
+    assert_eq!(
+        calls.executed(),
+        vec!["up a", "up b", "down b", "down b", "down a"]
+    );
+    assert_eq!(
+        statuses(&store),
+        vec![
+            ("a".to_string(), MigrationStatus::RolledBack),
+            ("b".to_string(), MigrationStatus::RolledBack),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn observer_is_notified_about_errors_before_the_run() {
+    let recorder = Recorder::default();
+    let migrator = |migrations| {
+        Migrator::builder()
+            .with_memory_store(MemoryMigrationStore::new())
+            .with_observer(recorder.clone())
+            .build(migrations)
+    };
+
+    let res = migrator(vec![Stub::boxed("a", vec![]), Stub::boxed("a", vec![])])
+        .up()
+        .await;
+    assert!(matches!(
+        res,
+        Err(MigrationExecution::PassedMigrationsWithDuplicatedIds { .. })
+    ));
+
+    let res = migrator(vec![Stub::boxed("a", vec![])])
+        .up_to("b".to_string())
+        .await;
+    assert!(matches!(
+        res,
+        Err(MigrationExecution::MigrationFromVecNotFound { .. })
+    ));
+
+    assert_eq!(
+        *recorder.events.lock().unwrap(),
+        vec![
+            "on_failure before the run",
+            "after_all false",
+            "on_failure before the run",
+            "after_all false",
+        ]
+    );
+}
@@ -0,0 +1,98 @@
+//! These tests check that observers are notified about every step of a run
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use mongodb_migrator::{
+    error::MigrationExecution,
+    migration::Migration,
+    migration_record::MigrationRecord,
+    migrator::{
+        execution_strategy::ExecutionStrategy,
+        observer::{MigrationObserver, MigrationRun},
+        Migrator,
+    },
+};
+
+use super::utils::{TestDb, M0, M1, M3};
+
+/// Collects notifications as readable lines
+#[derive(Clone, Default)]
+pub struct Recorder {
+    pub events: Arc<Mutex<Vec<String>>>,
+}
+
+#[async_trait]
+impl MigrationObserver for Recorder {
+    async fn before_all(&self, run: &MigrationRun) {
+        self.events
+            .lock()
+            .unwrap()
+            .push(format!("before_all {:?}", run.migrations_ids));
+    }
+
+    async fn before_each(&self, _run: &MigrationRun, migration_id: &str) {
+        self.events
+            .lock()
+            .unwrap()
+            .push(format!("before_each {migration_id}"));
+    }
+
+    async fn after_each(&self, _run: &MigrationRun, record: &MigrationRecord) {
+        self.events
+            .lock()
+            .unwrap()
+            .push(format!("after_each {} {:?}", record._id, record.status));
+    }
+
+    async fn on_failure(&self, _run: &MigrationRun, error: &MigrationExecution) {
+        let migration_id = match error {
+            MigrationExecution::FinishedAndSavedAsFail { migration_id, .. }
+            | MigrationExecution::DependencyFailed { migration_id, .. } => migration_id,
+            MigrationExecution::PassedMigrationsWithDuplicatedIds { .. }
+            | MigrationExecution::MigrationFromVecNotFound { .. } => "before the run",
+            _ => unreachable!(),
+        };
+        self.events
+            .lock()
+            .unwrap()
+            .push(format!("on_failure {migration_id}"));
+    }
+
+    async fn after_all(&self, _run: &MigrationRun, result: &Result<(), MigrationExecution>) {
+        self.events
+            .lock()
+            .unwrap()
+            .push(format!("after_all {}", result.is_ok()));
+    }
+}
+
+pub async fn observer_is_notified_about_every_step(t: &TestDb) {
+    let recorder = Recorder::default();
+    let migrations: Vec<Box<dyn Migration>> =
+        vec![Box::new(M0 {}), Box::new(M3 {}), Box::new(M1 {})];
+
+    let res = Migrator::builder()
+        .with_conn(t.db.clone())
+        .with_observer(recorder.clone())
+        .with_execution_strategy(ExecutionStrategy::TryAll)
+        .build(migrations)
+        .up()
+        .await;
+
+    assert!(res.is_err());
+    let (m0, m3, m1) = (M0 {}.get_id(), M3 {}.get_id(), M1 {}.get_id());
+    assert_eq!(
+        *recorder.events.lock().unwrap(),
+        vec![
+            format!("before_all {:?}", [m0, m3, m1]),
+            format!("before_each {m0}"),
+            format!("after_each {m0} Success"),
+            format!("before_each {m3}"),
+            format!("after_each {m3} Fail"),
+            format!("on_failure {m3}"),
+            format!("before_each {m1}"),
+            format!("after_each {m1} Success"),
+            "after_all false".to_string(),
+        ]
+    );
+}
@@ -0,0 +1,143 @@
+//! These tests check that independent migrations are executed concurrently only when it's enabled
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use mongodb_migrator::{
+    migration::Migration,
+    migration_status::MigrationStatus,
+    migrator::{parallel::ParallelConfig, Env},
+};
+
+use super::utils::{get_status, init_migrator_with_migrations, TestDb};
+
+struct SlowA {}
+struct SlowB {}
+struct Failing {}
+struct After {}
+
+async fn slow(env: Env) -> Result<()> {
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    env.db
+        .expect("db is available")
+        .collection("indexed")
+        .insert_one(bson::doc! {"migration_id": env.migration_id})
+        .await?;
+
+    Ok(())
+}
+
+#[async_trait]
+impl Migration for SlowA {
+    async fn up(&self, env: Env) -> Result<()> {
+        slow(env).await
+    }
+
+    fn group(&self) -> Option<String> {
+        Some("indexes".to_string())
+    }
+}
+
+#[async_trait]
+impl Migration for SlowB {
+    async fn up(&self, env: Env) -> Result<()> {
+        slow(env).await
+    }
+
+    fn group(&self) -> Option<String> {
+        Some("indexes".to_string())
+    }
+}
+
+#[async_trait]
+impl Migration for Failing {
+    async fn up(&self, _env: Env) -> Result<()> {
+        Err(anyhow::anyhow!("test error"))
+    }
+
+    fn group(&self) -> Option<String> {
+        Some("indexes".to_string())
+    }
+}
+
+#[async_trait]
+impl Migration for After {
+    async fn up(&self, _env: Env) -> Result<()> {
+        Ok(())
+    }
+
+    fn depends_on(&self) -> Vec<String> {
+        vec![SlowB {}.get_id().to_string()]
+    }
+}
+
+pub async fn migrations_of_the_same_group_overlap_when_enabled(t: &TestDb) {
+    let migrations: Vec<Box<dyn Migration>> =
+        vec![Box::new(SlowA {}), Box::new(SlowB {}), Box::new(After {})];
+    let mut migrator = init_migrator_with_migrations(t.db.clone(), migrations);
+    migrator.set_parallel_config(ParallelConfig { max_concurrency: 2 });
+
+    migrator.up().await.unwrap();
+
+    let run_id = migrator.get_last_run_id().await.unwrap().unwrap();
+    let report = migrator.get_run_report(&run_id).await.unwrap();
+    assert!(report.has_overlaps());
+
+    let overlapped_with = |migration_id: &str| {
+        report
+            .entries
+            .iter()
+            .find(|entry| entry.migration_id == migration_id)
+            .unwrap()
+            .overlapped_with
+            .clone()
+    };
+    assert_eq!(
+        overlapped_with(SlowA {}.get_id()),
+        vec![SlowB {}.get_id().to_string()]
+    );
+    assert_eq!(
+        overlapped_with(SlowB {}.get_id()),
+        vec![SlowA {}.get_id().to_string()]
+    );
+    // it waits for its dependency
+    assert!(overlapped_with(After {}.get_id()).is_empty());
+    for entry in &report.entries {
+        assert_eq!(entry.status, MigrationStatus::Success);
+    }
+}
+
+pub async fn migrations_are_executed_one_by_one_by_default(t: &TestDb) {
+    let migrations: Vec<Box<dyn Migration>> =
+        vec![Box::new(SlowA {}), Box::new(SlowB {}), Box::new(After {})];
+    let migrator = init_migrator_with_migrations(t.db.clone(), migrations);
+
+    migrator.up().await.unwrap();
+
+    let run_id = migrator.get_last_run_id().await.unwrap().unwrap();
+    let report = migrator.get_run_report(&run_id).await.unwrap();
+    assert_eq!(report.entries.len(), 3);
+    assert!(!report.has_overlaps());
+}
+
+pub async fn failed_migration_lets_its_batch_finish_and_stops_next_ones(t: &TestDb) {
+    let migrations: Vec<Box<dyn Migration>> =
+        vec![Box::new(Failing {}), Box::new(SlowB {}), Box::new(After {})];
+    let mut migrator = init_migrator_with_migrations(t.db.clone(), migrations);
+    migrator.set_parallel_config(ParallelConfig::default());
+
+    assert!(migrator.up().await.is_err());
+
+    assert_eq!(
+        get_status(t, Failing {}.get_id()).await,
+        MigrationStatus::Fail
+    );
+    assert_eq!(
+        get_status(t, SlowB {}.get_id()).await,
+        MigrationStatus::Success
+    );
+    assert_eq!(
+        get_status(t, After {}.get_id()).await,
+        MigrationStatus::Fail
+    );
+}
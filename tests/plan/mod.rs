@@ -0,0 +1,82 @@
+//! These tests check that a plan describes what would be executed without executing anything
+use mongodb_migrator::{
+    migration::Migration,
+    migration_record::MigrationRecord,
+    migrator::{
+        lock::LockConfig,
+        plan::{MigrationPlanEntry, PlanDecision, RunReason, SkipReason},
+    },
+    operation_type::OperationType,
+};
+
+use super::utils::{init_migrator_with_migrations, TestDb, Users, M0, M1, M2, M3};
+
+pub async fn plan_explains_decisions_and_executes_nothing(t: &TestDb) {
+    let succeeded =
+        MigrationRecord::migration_start(M0 {}.get_id().to_string()).migration_succeeded();
+    let failed = MigrationRecord::migration_start(M3 {}.get_id().to_string()).migration_failed();
+    let in_progress = MigrationRecord::migration_start(M2 {}.get_id().to_string());
+    t.db.collection::<MigrationRecord>("migrations")
+        .insert_many(vec![&succeeded, &failed, &in_progress])
+        .await
+        .unwrap();
+
+    let migrations: Vec<Box<dyn Migration>> = vec![
+        Box::new(M0 {}),
+        Box::new(M3 {}),
+        Box::new(M1 {}),
+        Box::new(M2 {}),
+    ];
+    let mut migrator = init_migrator_with_migrations(t.db.clone(), migrations);
+
+    let plan = migrator.plan(OperationType::Up).await.unwrap();
+
+    assert_eq!(
+        plan.entries,
+        vec![
+            MigrationPlanEntry {
+                migration_id: M0 {}.get_id().to_string(),
+                decision: PlanDecision::Skip(SkipReason::AlreadySucceeded),
+            },
+            MigrationPlanEntry {
+                migration_id: M3 {}.get_id().to_string(),
+                decision: PlanDecision::Run(RunReason::PreviouslyFailed),
+            },
+            MigrationPlanEntry {
+                migration_id: M1 {}.get_id().to_string(),
+                decision: PlanDecision::Run(RunReason::NeverRun),
+            },
+            MigrationPlanEntry {
+                migration_id: M2 {}.get_id().to_string(),
+                decision: PlanDecision::Skip(SkipReason::InProgress),
+            },
+        ]
+    );
+    assert_eq!(
+        plan.ids_to_run(),
+        vec![M3 {}.get_id().to_string(), M1 {}.get_id().to_string()]
+    );
+
+    // nobody holds the lock so nobody can execute the in progress migration
+    migrator.set_lock_config(LockConfig::default());
+    let plan = migrator.plan(OperationType::Up).await.unwrap();
+    assert_eq!(
+        plan.entries[3].decision,
+        PlanDecision::Run(RunReason::StaleInProgress)
+    );
+
+    assert_eq!(
+        t.db.collection::<Users>("users")
+            .count_documents(bson::doc! {})
+            .await
+            .unwrap(),
+        0
+    );
+    assert_eq!(
+        t.db.collection::<MigrationRecord>("migrations")
+            .count_documents(bson::doc! {})
+            .await
+            .unwrap(),
+        3
+    );
+}
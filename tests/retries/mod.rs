@@ -1 +1,180 @@
-// TODO(kakoc): add retries tests
+//! These tests check that failed migrations are retried within the same run
+//! and re-executed by the next runs
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use mongodb_migrator::{
+    error::MigrationExecution,
+    migration::Migration,
+    migration_record::MigrationRecord,
+    migration_status::MigrationStatus,
+    migrator::{
+        store::{MemoryMigrationStore, StoreError},
+        with_retries::RetryPolicy,
+        Env, Migrator,
+    },
+};
+
+use super::store::AuditingStore;
+use super::utils::{
+    get_record, get_status, init_migrator_with_migrations, FailsFirstAttempts, TestDb, M0,
+};
+
+/// Imitates someone else who modifies the record while the migration is executed
+struct ModifiesItsRecord {}
+
+#[async_trait]
+impl Migration for ModifiesItsRecord {
+    async fn up(&self, env: Env) -> Result<()> {
+        env.db
+            .expect("db is available")
+            .collection::<MigrationRecord>("migrations")
+            .update_one(
+                bson::doc! {"_id": self.get_id()},
+                bson::doc! {"$inc": {"version": 1_i64}},
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+pub async fn failed_migration_succeeds_after_retry(t: &TestDb) {
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let migration = FailsFirstAttempts {
+        attempts: attempts.clone(),
+        fails_count: 2,
+        ..Default::default()
+    };
+    let migration_id = migration.get_id().to_string();
+    let migrations: Vec<Box<dyn Migration>> = vec![Box::new(migration), Box::new(M0 {})];
+
+    let res = mongodb_migrator::migrator::default::DefaultMigrator::new()
+        .with_conn(t.db.clone())
+        .with_retries(2, Duration::from_millis(1))
+        .with_migrations_vec(migrations)
+        .up()
+        .await;
+
+    assert!(res.is_ok());
+    assert_eq!(attempts.load(Ordering::SeqCst), 3);
+
+    let record = get_record(t, &migration_id).await.unwrap();
+    assert_eq!(record.status, MigrationStatus::Success);
+    assert_eq!(record.attempts, 3);
+    assert_eq!(record.error, None);
+    assert_eq!(
+        get_status(t, M0 {}.get_id()).await,
+        MigrationStatus::Success
+    );
+}
+
+pub async fn failed_migration_is_rerun_by_next_run(t: &TestDb) {
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let migration_id = FailsFirstAttempts {
+        attempts: attempts.clone(),
+        fails_count: 1,
+        ..Default::default()
+    }
+    .get_id()
+    .to_string();
+    let init_migrations = || -> Vec<Box<dyn Migration>> {
+        vec![
+            Box::new(FailsFirstAttempts {
+                attempts: attempts.clone(),
+                fails_count: 1,
+                ..Default::default()
+            }),
+            Box::new(M0 {}),
+        ]
+    };
+
+    let res = init_migrator_with_migrations(t.db.clone(), init_migrations())
+        .up()
+        .await;
+
+    assert!(res.is_err());
+    assert_eq!(get_status(t, &migration_id).await, MigrationStatus::Fail);
+    assert_eq!(get_status(t, M0 {}.get_id()).await, MigrationStatus::Fail);
+
+    let res = init_migrator_with_migrations(t.db.clone(), init_migrations())
+        .up()
+        .await;
+
+    assert!(res.is_ok());
+    let record = get_record(t, &migration_id).await.unwrap();
+    assert_eq!(record.status, MigrationStatus::Success);
+    assert_eq!(record.attempts, 2);
+    assert_eq!(
+        get_status(t, M0 {}.get_id()).await,
+        MigrationStatus::Success
+    );
+}
+
+pub async fn record_modified_during_execution_is_not_overwritten(t: &TestDb) {
+    let migrations: Vec<Box<dyn Migration>> = vec![Box::new(ModifiesItsRecord {})];
+
+    let res = init_migrator_with_migrations(t.db.clone(), migrations)
+        .up()
+        .await;
+
+    match res {
+        Err(MigrationExecution::MigrationRecordModifiedConcurrently {
+            migration_id,
+            expected_version,
+            ..
+        }) => {
+            assert_eq!(migration_id, ModifiesItsRecord {}.get_id());
+            assert_eq!(expected_version, 1);
+        }
+        _ => unreachable!(),
+    }
+    assert_eq!(
+        get_status(t, ModifiesItsRecord {}.get_id()).await,
+        MigrationStatus::InProgress
+    );
+}
+
+#[tokio::test]
+async fn migration_isnt_executed_again_when_its_result_wasnt_saved() {
+    let store = MemoryMigrationStore::new();
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let migration = FailsFirstAttempts {
+        attempts: attempts.clone(),
+        ..Default::default()
+    };
+    let migration_id = migration.get_id().to_string();
+    let migrator = Migrator::builder()
+        .with_memory_store(store.clone())
+        .with_store(AuditingStore {
+            inner: store.clone(),
+            writes: Default::default(),
+            rejected: Mutex::new(Some((
+                "finish_history",
+                StoreError::from(mongodb::error::Error::from(std::io::Error::other(
+                    "connection reset",
+                ))),
+            ))),
+        })
+        .with_retry_policy(RetryPolicy::exponential(3, Duration::from_millis(1)))
+        .build(vec![Box::new(migration)]);
+
+    migrator.up().await.unwrap();
+
+    assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    let migration_record = store.records()[&migration_id].clone();
+    assert_eq!(
+        (migration_record.status, migration_record.attempts),
+        (MigrationStatus::Success, 1)
+    );
+    let history = store.history();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].status, MigrationStatus::Success);
+}
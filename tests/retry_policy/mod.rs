@@ -0,0 +1,71 @@
+//! These tests check which errors are retried and that a migration can override the retry policy
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use mongodb_migrator::{
+    error::MigrationExecution, migration::Migration, migration_status::MigrationStatus,
+    migrator::with_retries::RetryPolicy,
+};
+
+use super::utils::{get_status, init_migrator_with_migrations, FailsFirstAttempts, TestDb};
+
+pub async fn not_transient_error_is_not_retried_by_default_predicate(t: &TestDb) {
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let migrations: Vec<Box<dyn Migration>> = vec![Box::new(FailsFirstAttempts {
+        attempts: attempts.clone(),
+        fails_count: 1,
+        retry_policy: None,
+    })];
+
+    let res = init_migrator_with_migrations(t.db.clone(), migrations)
+        .set_retry_policy(RetryPolicy::exponential(3, Duration::from_millis(1)))
+        .up()
+        .await;
+
+    assert!(res.is_err());
+    assert_eq!(attempts.load(Ordering::SeqCst), 1);
+}
+
+pub async fn migration_retry_policy_overrides_migrator_one(t: &TestDb) {
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let migration = FailsFirstAttempts {
+        attempts: attempts.clone(),
+        fails_count: 2,
+        retry_policy: Some(RetryPolicy::fixed(2, Duration::from_millis(1))),
+    };
+    let migration_id = migration.get_id().to_string();
+    let migrations: Vec<Box<dyn Migration>> = vec![Box::new(migration)];
+
+    let res = init_migrator_with_migrations(t.db.clone(), migrations)
+        .up()
+        .await;
+
+    assert!(res.is_ok());
+    assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    assert_eq!(get_status(t, &migration_id).await, MigrationStatus::Success);
+}
+
+#[test]
+fn delay_is_capped_for_large_attempts_and_jitter() {
+    let error = MigrationExecution::TransactionsNotSupported {
+        migration_id: "m".to_string(),
+        next_not_executed_migrations_ids: vec![],
+    };
+    let retry_policy = RetryPolicy {
+        jitter: 5.0,
+        is_retryable: Arc::new(|_| true),
+        ..RetryPolicy::exponential(usize::MAX, Duration::from_millis(100))
+    };
+
+    for attempt in [1, 69, 1_000, u32::MAX] {
+        let delay = retry_policy
+            .next_delay(attempt, Duration::ZERO, &error)
+            .unwrap();
+        assert!(delay <= retry_policy.max_delay * 2);
+    }
+}
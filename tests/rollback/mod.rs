@@ -0,0 +1,72 @@
+//! These tests check that only applied migrations are rolled back
+//! and rolled back migrations become pending again
+use mongodb_migrator::{
+    migration::Migration, migration_status::MigrationStatus, operation_type::OperationType,
+};
+
+use super::utils::{get_status, init_migrator_with_migrations, TestDb, M0, M1, M2, M3};
+
+pub async fn down_rollbacks_only_applied_migrations(t: &TestDb) {
+    let migrations: Vec<Box<dyn Migration>> =
+        vec![Box::new(M0 {}), Box::new(M3 {}), Box::new(M1 {})];
+    let migrator = init_migrator_with_migrations(t.db.clone(), migrations);
+
+    let _ = migrator.up().await;
+    migrator.down().await.unwrap();
+
+    assert_eq!(
+        get_status(t, M0 {}.get_id()).await,
+        MigrationStatus::RolledBack
+    );
+    assert_eq!(get_status(t, M3 {}.get_id()).await, MigrationStatus::Fail);
+    assert_eq!(get_status(t, M1 {}.get_id()).await, MigrationStatus::Fail);
+    assert!(migrator
+        .get_history_by_migration_id(M3 {}.get_id())
+        .await
+        .unwrap()
+        .iter()
+        .all(|v| v.direction == OperationType::Up));
+}
+
+pub async fn rolled_back_migrations_are_executed_by_next_up(t: &TestDb) {
+    let migrations: Vec<Box<dyn Migration>> =
+        vec![Box::new(M0 {}), Box::new(M1 {}), Box::new(M2 {})];
+    let migrator = init_migrator_with_migrations(t.db.clone(), migrations);
+
+    migrator.up().await.unwrap();
+    migrator.down_last(1).await.unwrap();
+
+    assert_eq!(
+        get_status(t, M1 {}.get_id()).await,
+        MigrationStatus::Success
+    );
+    assert_eq!(
+        get_status(t, M2 {}.get_id()).await,
+        MigrationStatus::RolledBack
+    );
+
+    migrator.up().await.unwrap();
+
+    assert_eq!(
+        get_status(t, M2 {}.get_id()).await,
+        MigrationStatus::Success
+    );
+    assert_eq!(
+        migrator
+            .get_history_by_migration_id(M2 {}.get_id())
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|v| v.direction)
+            .collect::<Vec<OperationType>>(),
+        vec![OperationType::Up, OperationType::Down, OperationType::Up]
+    );
+    assert_eq!(
+        migrator
+            .get_history_by_migration_id(M1 {}.get_id())
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+}
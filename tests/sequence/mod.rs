@@ -92,10 +92,9 @@ pub async fn down_migrations_executed_in_specified_order(t: &TestDb) {
         .map(|m| m.get_id().to_string())
         .collect::<Vec<String>>();
 
-    init_migrator_with_migrations(t.db.clone(), migrations)
-        .down()
-        .await
-        .unwrap();
+    let migrator = init_migrator_with_migrations(t.db.clone(), migrations);
+    migrator.up().await.unwrap();
+    migrator.down().await.unwrap();
 
     let all_records =
         t.db.collection("migrations")
@@ -30,6 +30,7 @@ pub async fn server_runs_migrations_by_id() {
                     db: DbParams {
                         connection_string: format!("mongodb://localhost:{}/", host_port),
                         log_into_db_name: "test".to_string(),
+                        target_db_name: None,
                     },
                     migrations,
                 },
@@ -41,8 +42,6 @@ pub async fn server_runs_migrations_by_id() {
 
         check_ups(&db).await;
 
-        db.drop().await.expect("test db deleted");
-
         check_downs(&db).await;
     })
     .await;
@@ -55,6 +55,7 @@ pub async fn down_migrations_executed_in_single_manner(t: &TestDb) {
         .collect::<Vec<String>>();
 
     let migrator = init_migrator_with_migrations(t.db.clone(), migrations); // .unwrap();
+    migrator.up().await.unwrap();
 
     migrator
         .down_single_from_vec(M2 {}.get_id().to_string())
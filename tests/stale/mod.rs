@@ -0,0 +1,87 @@
+//! These tests check that migrations left in progress by a crashed migrator aren't skipped silently
+use chrono::Utc;
+use mongodb_migrator::{
+    error::MigrationExecution,
+    migration::Migration,
+    migration_record::MigrationRecord,
+    migration_status::MigrationStatus,
+    migrator::stale::{StaleConfig, StalePolicy},
+};
+
+use super::utils::{get_record, init_migrator_with_migrations, TestDb, M0, M1};
+
+async fn insert_in_progress(t: &TestDb, migration_id: &str, heartbeat_ago: chrono::Duration) {
+    let heartbeat_at = Utc::now() - heartbeat_ago;
+    let migration_record = MigrationRecord {
+        start_date: Some(heartbeat_at),
+        heartbeat_at: Some(heartbeat_at),
+        ..MigrationRecord::migration_start(migration_id.to_string()).owned_by("crashed".to_string())
+    };
+
+    t.db.collection::<MigrationRecord>("migrations")
+        .insert_one(migration_record)
+        .await
+        .unwrap();
+}
+
+fn stale_config(policy: StalePolicy) -> StaleConfig {
+    StaleConfig {
+        policy,
+        ..Default::default()
+    }
+}
+
+pub async fn block_policy_stops_until_repaired(t: &TestDb) {
+    insert_in_progress(t, M0 {}.get_id(), chrono::Duration::hours(2)).await;
+    let migrations: Vec<Box<dyn Migration>> = vec![Box::new(M0 {}), Box::new(M1 {})];
+    let mut migrator = init_migrator_with_migrations(t.db.clone(), migrations);
+    migrator.set_stale_config(stale_config(StalePolicy::Block));
+
+    match migrator.up().await {
+        Err(MigrationExecution::StaleMigrationsFound { migrations_ids }) => {
+            assert_eq!(migrations_ids, vec![M0 {}.get_id().to_string()]);
+        }
+        _ => unreachable!(),
+    }
+    assert!(get_record(t, M1 {}.get_id()).await.is_none());
+
+    assert_eq!(
+        migrator.repair().await.unwrap(),
+        vec![M0 {}.get_id().to_string()]
+    );
+    assert_eq!(
+        get_record(t, M0 {}.get_id()).await.unwrap().status,
+        MigrationStatus::Interrupted
+    );
+
+    migrator.up().await.unwrap();
+
+    let migration_record = get_record(t, M0 {}.get_id()).await.unwrap();
+    assert_eq!(migration_record.status, MigrationStatus::Success);
+    assert_eq!(migration_record.attempts, 2);
+    assert_eq!(migration_record.owner, Some(migrator.owner.clone()));
+    assert_eq!(
+        get_record(t, M1 {}.get_id()).await.unwrap().status,
+        MigrationStatus::Success
+    );
+}
+
+pub async fn mark_interrupted_policy_reruns_stale_and_skips_alive(t: &TestDb) {
+    insert_in_progress(t, M0 {}.get_id(), chrono::Duration::hours(2)).await;
+    insert_in_progress(t, M1 {}.get_id(), chrono::Duration::zero()).await;
+    let migrations: Vec<Box<dyn Migration>> = vec![Box::new(M0 {}), Box::new(M1 {})];
+    let mut migrator = init_migrator_with_migrations(t.db.clone(), migrations);
+    migrator.set_stale_config(stale_config(StalePolicy::MarkInterrupted));
+
+    migrator.up().await.unwrap();
+
+    assert_eq!(
+        get_record(t, M0 {}.get_id()).await.unwrap().status,
+        MigrationStatus::Success
+    );
+    // its owner is still alive, so nobody else executes it
+    assert_eq!(
+        get_record(t, M1 {}.get_id()).await.unwrap().status,
+        MigrationStatus::InProgress
+    );
+}
@@ -0,0 +1,94 @@
+//! These tests check that records of several target databases can be kept in a central one
+use mongodb_migrator::{
+    migration::Migration, migration_record::MigrationRecord, migration_status::MigrationStatus,
+    migrator::DefaultMigrator,
+};
+
+use super::utils::{TestDb, M0};
+
+pub async fn records_of_targets_are_kept_in_state_db(t: &TestDb) {
+    let client = t.db.client();
+    let state_db = client.database("central");
+    let other_target = client.database("other_target");
+
+    for target in [&t.db, &other_target] {
+        let migrations: Vec<Box<dyn Migration>> = vec![Box::new(M0 {})];
+        let migrator = DefaultMigrator::new()
+            .with_conn(target.clone())
+            .with_state_db(state_db.clone())
+            .with_collection_name(format!("{}_migrations", target.name()))
+            .with_migrations_vec(migrations);
+
+        migrator.up().await.unwrap();
+
+        // migrations are applied to the target
+        assert_eq!(
+            target
+                .collection::<bson::Document>("users")
+                .count_documents(bson::doc! {})
+                .await
+                .unwrap(),
+            1
+        );
+        assert!(target
+            .list_collection_names()
+            .await
+            .unwrap()
+            .iter()
+            .all(|name| !name.contains("migrations")));
+
+        let migration_record = state_db
+            .collection::<MigrationRecord>(&format!("{}_migrations", target.name()))
+            .find_one(bson::doc! {"_id": M0 {}.get_id()})
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(migration_record.status, MigrationStatus::Success);
+        assert_eq!(migration_record.target.as_deref(), Some(target.name()));
+
+        let history = migrator
+            .get_history_by_migration_id(M0 {}.get_id())
+            .await
+            .unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].target.as_deref(), Some(target.name()));
+    }
+
+    state_db.drop().await.unwrap();
+    other_target.drop().await.unwrap();
+}
+
+pub async fn state_db_keeps_default_collection_name(t: &TestDb) {
+    let migrations: Vec<Box<dyn Migration>> = vec![Box::new(M0 {})];
+    DefaultMigrator::new()
+        .with_conn(t.db.clone())
+        .with_migrations_vec(migrations)
+        .up()
+        .await
+        .unwrap();
+
+    // the state database is the one which already keeps records
+    let migrations: Vec<Box<dyn Migration>> = vec![Box::new(M0 {})];
+    DefaultMigrator::new()
+        .with_conn(t.db.clone())
+        .with_state_db(t.db.clone())
+        .with_migrations_vec(migrations)
+        .up()
+        .await
+        .unwrap();
+
+    assert_eq!(
+        t.db.collection::<bson::Document>("users")
+            .count_documents(bson::doc! {})
+            .await
+            .unwrap(),
+        1
+    );
+    assert_eq!(
+        t.db.collection::<MigrationRecord>("migrations")
+            .count_documents(bson::doc! {})
+            .await
+            .unwrap(),
+        1
+    );
+}
@@ -0,0 +1,146 @@
+//! These tests check that records of older and newer migrators are read
+//! and records of older ones are upgraded to the current state format
+use chrono::Utc;
+use mongodb_migrator::{
+    error::MigrationExecution,
+    migration::Migration,
+    migration_record::MigrationRecord,
+    migration_status::MigrationStatus,
+    migrator::{
+        plan::{PlanDecision, RunReason, SkipReason},
+        store::{MemoryMigrationStore, MigrationStore},
+        Migrator,
+    },
+    operation_type::OperationType,
+    state_metadata::{StateMetadata, STATE_FORMAT_VERSION},
+};
+
+use super::utils::{init_migrator_with_migrations, TestDb, M0, M1, M2};
+
+pub async fn legacy_records_are_upgraded_and_unknown_statuses_are_skipped(t: &TestDb) {
+    let now = bson::to_bson(&Utc::now()).unwrap();
+    // M0 is applied by 0.1.x, M2 has a status of a newer migrator
+    t.db.collection::<bson::Document>("migrations")
+        .insert_many(vec![
+            bson::doc! {
+                "_id": M0 {}.get_id(),
+                "start_date": &now,
+                "end_date": &now,
+                "status": "Success",
+                "duration": 0_i64,
+            },
+            bson::doc! {"_id": M2 {}.get_id(), "status": "Paused", "version": 3_i64},
+        ])
+        .await
+        .unwrap();
+
+    let migrations: Vec<Box<dyn Migration>> =
+        vec![Box::new(M0 {}), Box::new(M1 {}), Box::new(M2 {})];
+    let migrator = init_migrator_with_migrations(t.db.clone(), migrations);
+
+    assert_eq!(
+        migrator
+            .plan(OperationType::Up)
+            .await
+            .unwrap()
+            .entries
+            .into_iter()
+            .map(|entry| entry.decision)
+            .collect::<Vec<PlanDecision>>(),
+        vec![
+            PlanDecision::Skip(SkipReason::AlreadySucceeded),
+            PlanDecision::Run(RunReason::NeverRun),
+            PlanDecision::Skip(SkipReason::UnknownStatus),
+        ]
+    );
+
+    migrator.up().await.unwrap();
+
+    let get_record = |migration_id: &'static str| async move {
+        t.db.collection::<MigrationRecord>("migrations")
+            .find_one(bson::doc! {"_id": migration_id})
+            .await
+            .unwrap()
+            .unwrap()
+    };
+    let migration_record = get_record(M0 {}.get_id()).await;
+    assert_eq!(migration_record.status, MigrationStatus::Success);
+    assert_eq!(migration_record.version, 1);
+    assert_eq!(migration_record.attempts, 1);
+    assert_eq!(migration_record.target.as_deref(), Some(t.db.name()));
+    assert_eq!(
+        get_record(M1 {}.get_id()).await.status,
+        MigrationStatus::Success
+    );
+    assert_eq!(
+        get_record(M2 {}.get_id()).await.status,
+        MigrationStatus::Unknown
+    );
+
+    let metadata =
+        t.db.collection::<StateMetadata>("migrations_metadata")
+            .find_one(bson::doc! {})
+            .await
+            .unwrap()
+            .unwrap();
+    assert_eq!(metadata.format_version, STATE_FORMAT_VERSION);
+}
+
+#[tokio::test]
+async fn legacy_record_is_upgraded_once_by_first_run() {
+    let store = MemoryMigrationStore::new();
+    store.insert_record(MigrationRecord {
+        attempts: 0,
+        version: 0,
+        ..MigrationRecord::migration_start(M0 {}.get_id().to_string()).migration_succeeded()
+    });
+    let migrations: Vec<Box<dyn Migration>> = vec![Box::new(M0 {})];
+    let migrator = Migrator::builder()
+        .with_memory_store(store.clone())
+        .build(migrations);
+
+    migrator.up().await.unwrap();
+    migrator.up().await.unwrap();
+
+    let migration_record = store.records()[M0 {}.get_id()].clone();
+    assert_eq!(migration_record.status, MigrationStatus::Success);
+    assert_eq!(
+        (migration_record.attempts, migration_record.version),
+        (1, 1)
+    );
+    assert_eq!(
+        store.metadata().map(|metadata| metadata.format_version),
+        Some(STATE_FORMAT_VERSION)
+    );
+}
+
+#[tokio::test]
+async fn state_of_newer_format_is_not_modified() {
+    let store = MemoryMigrationStore::new();
+    let metadata = StateMetadata {
+        format_version: STATE_FORMAT_VERSION + 1,
+        ..StateMetadata::current("newer".to_string())
+    };
+    store.insert_record(MigrationRecord {
+        version: 0,
+        ..MigrationRecord::migration_start(M0 {}.get_id().to_string()).migration_succeeded()
+    });
+    let migrations: Vec<Box<dyn Migration>> = vec![Box::new(M0 {}), Box::new(M1 {})];
+    let migrator = Migrator::builder()
+        .with_memory_store(store.clone())
+        .build(migrations);
+    store.save_metadata(&metadata).await.unwrap();
+
+    match migrator.up().await {
+        Err(MigrationExecution::StateFormatNotSupported {
+            format_version,
+            supported_format_version,
+        }) => {
+            assert_eq!(format_version, STATE_FORMAT_VERSION + 1);
+            assert_eq!(supported_format_version, STATE_FORMAT_VERSION);
+        }
+        res => panic!("unexpected result: {res:?}"),
+    }
+    assert_eq!(store.records().len(), 1);
+    assert_eq!(store.records()[M0 {}.get_id()].version, 0);
+}
@@ -0,0 +1,233 @@
+//! These tests check that the state of migrations is kept by the passed store
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mongodb::ClientSession;
+use mongodb_migrator::{
+    error::MigrationExecution,
+    migration::Migration,
+    migration_history::MigrationHistoryRecord,
+    migration_record::MigrationRecord,
+    migration_status::MigrationStatus,
+    migrator::{
+        store::{
+            HistoryFilter, MemoryMigrationStore, MigrationStore, MongoMigrationStore, StoreError,
+        },
+        DefaultMigrator, Migrator,
+    },
+    state_metadata::StateMetadata,
+};
+
+use super::utils::{TestDb, M0, M1};
+
+/// Records every write of records and passes it to the inner store,
+/// the first write of the `rejected` operation fails with its error
+pub struct AuditingStore<S> {
+    pub inner: S,
+    pub writes: Arc<Mutex<Vec<String>>>,
+    pub rejected: Mutex<Option<(&'static str, StoreError)>>,
+}
+
+impl<S> AuditingStore<S> {
+    fn audit(&self, operation: &'static str, migration_id: &str) -> Result<(), StoreError> {
+        self.check(operation)?;
+        self.writes
+            .lock()
+            .unwrap()
+            .push(format!("{operation} {migration_id}"));
+
+        Ok(())
+    }
+
+    fn check(&self, operation: &'static str) -> Result<(), StoreError> {
+        let mut rejected = self.rejected.lock().unwrap();
+        match rejected.take() {
+            Some((rejected_operation, error)) if rejected_operation == operation => Err(error),
+            other => {
+                *rejected = other;
+                Ok(())
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<S: MigrationStore> MigrationStore for AuditingStore<S> {
+    async fn load_records(
+        &self,
+        ids: &[String],
+    ) -> Result<BTreeMap<String, MigrationRecord>, StoreError> {
+        self.inner.load_records(ids).await
+    }
+
+    async fn load_all_records(&self) -> Result<BTreeMap<String, MigrationRecord>, StoreError> {
+        self.inner.load_all_records().await
+    }
+
+    async fn load_record(&self, migration_id: &str) -> Result<Option<MigrationRecord>, StoreError> {
+        self.inner.load_record(migration_id).await
+    }
+
+    async fn begin(
+        &self,
+        migration_record: &MigrationRecord,
+        expected_version: i64,
+    ) -> Result<bool, StoreError> {
+        self.audit("begin", &migration_record._id)?;
+        self.inner.begin(migration_record, expected_version).await
+    }
+
+    async fn finish(
+        &self,
+        migration_record: &MigrationRecord,
+        expected_version: i64,
+        session: Option<&mut ClientSession>,
+    ) -> Result<bool, StoreError> {
+        self.audit("finish", &migration_record._id)?;
+        self.inner
+            .finish(migration_record, expected_version, session)
+            .await
+    }
+
+    async fn mark(
+        &self,
+        migration_record: &MigrationRecord,
+        expected_version: i64,
+    ) -> Result<bool, StoreError> {
+        self.audit("mark", &migration_record._id)?;
+        self.inner.mark(migration_record, expected_version).await
+    }
+
+    async fn mark_not_executed(
+        &self,
+        migration_record: &MigrationRecord,
+    ) -> Result<(), StoreError> {
+        self.audit("mark_not_executed", &migration_record._id)?;
+        self.inner.mark_not_executed(migration_record).await
+    }
+
+    async fn forget(&self, migration_id: &str, expected_version: i64) -> Result<bool, StoreError> {
+        self.audit("forget", migration_id)?;
+        self.inner.forget(migration_id, expected_version).await
+    }
+
+    async fn heartbeat(
+        &self,
+        migration_record: &MigrationRecord,
+        heartbeat_at: DateTime<Utc>,
+    ) -> Result<bool, StoreError> {
+        self.inner.heartbeat(migration_record, heartbeat_at).await
+    }
+
+    async fn append_history(
+        &self,
+        history_record: &MigrationHistoryRecord,
+    ) -> Result<(), StoreError> {
+        // attempts are appended when they are started and saved again when they are finished
+        self.check(match history_record.end_date {
+            Some(_) => "finish_history",
+            None => "append_history",
+        })?;
+        self.inner.append_history(history_record).await
+    }
+
+    async fn load_history(
+        &self,
+        filter: HistoryFilter,
+    ) -> Result<Vec<MigrationHistoryRecord>, StoreError> {
+        self.inner.load_history(filter).await
+    }
+
+    async fn last_run_id(&self) -> Result<Option<String>, StoreError> {
+        self.inner.last_run_id().await
+    }
+
+    async fn load_metadata(&self) -> Result<Option<StateMetadata>, StoreError> {
+        self.inner.load_metadata().await
+    }
+
+    async fn save_metadata(&self, metadata: &StateMetadata) -> Result<(), StoreError> {
+        self.inner.save_metadata(metadata).await
+    }
+}
+
+pub async fn state_is_kept_by_passed_store(t: &TestDb) {
+    let writes = Arc::new(Mutex::new(vec![]));
+    let migrations: Vec<Box<dyn Migration>> = vec![Box::new(M0 {}), Box::new(M1 {})];
+    let migrator = DefaultMigrator::new()
+        .with_conn(t.db.clone())
+        .with_store(AuditingStore {
+            inner: MongoMigrationStore::new(&t.db, "audited", "audited_history"),
+            writes: writes.clone(),
+            rejected: Mutex::new(None),
+        })
+        .with_migrations_vec(migrations);
+
+    migrator.up().await.unwrap();
+    migrator.forget(M1 {}.get_id().to_string()).await.unwrap();
+
+    assert_eq!(
+        *writes.lock().unwrap(),
+        vec![
+            format!("begin {}", M0 {}.get_id()),
+            format!("finish {}", M0 {}.get_id()),
+            format!("begin {}", M1 {}.get_id()),
+            format!("finish {}", M1 {}.get_id()),
+            format!("forget {}", M1 {}.get_id()),
+        ]
+    );
+
+    let migration_record =
+        t.db.collection::<MigrationRecord>("audited")
+            .find_one(bson::doc! {"_id": M0 {}.get_id()})
+            .await
+            .unwrap()
+            .unwrap();
+    assert_eq!(migration_record.status, MigrationStatus::Success);
+    // the default collections aren't used by a custom store
+    assert!(t
+        .db
+        .collection::<MigrationRecord>("migrations")
+        .find_one(bson::doc! {})
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(
+        migrator
+            .get_history_by_migration_id(M1 {}.get_id())
+            .await
+            .unwrap()
+            .len(),
+        2
+    );
+}
+
+#[tokio::test]
+async fn errors_of_custom_store_are_returned() {
+    let store = MemoryMigrationStore::new();
+    let migrations: Vec<Box<dyn Migration>> = vec![Box::new(M0 {})];
+    let migrator = Migrator::builder()
+        .with_memory_store(store.clone())
+        .with_store(AuditingStore {
+            inner: store.clone(),
+            writes: Default::default(),
+            rejected: Mutex::new(Some((
+                "begin",
+                StoreError::other(std::io::Error::other("begin is rejected by the audit")),
+            ))),
+        })
+        .build(migrations);
+
+    match migrator.up().await {
+        Err(MigrationExecution::InProgressStatusNotSaved {
+            additional_info: StoreError::Other(error),
+            ..
+        }) => assert_eq!(error.to_string(), "begin is rejected by the audit"),
+        res => panic!("unexpected result: {res:?}"),
+    }
+    assert!(store.records().is_empty());
+}
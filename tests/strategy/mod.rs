@@ -0,0 +1,53 @@
+//! These tests check how execution strategies react on failed migrations
+use mongodb_migrator::{
+    error::MigrationExecution, migration::Migration, migration_record::MigrationRecord,
+    migration_status::MigrationStatus, migrator::execution_strategy::ExecutionStrategy,
+};
+
+use super::utils::{init_migrator_with_migrations, TestDb, Users, M0, M1, M3};
+
+pub async fn try_all_executes_migrations_after_failed_one(t: &TestDb) {
+    let migrations: Vec<Box<dyn Migration>> =
+        vec![Box::new(M0 {}), Box::new(M3 {}), Box::new(M1 {})];
+
+    let res = init_migrator_with_migrations(t.db.clone(), migrations)
+        .set_execution_strategy(ExecutionStrategy::TryAll)
+        .up()
+        .await;
+
+    match res {
+        Err(MigrationExecution::TryAllFinishedWithFailures { failures }) => {
+            assert_eq!(failures.len(), 1);
+            assert_eq!(failures[0].0, M3 {}.get_id());
+            assert!(matches!(
+                failures[0].1,
+                MigrationExecution::FinishedAndSavedAsFail { .. }
+            ));
+        }
+        _ => unreachable!(),
+    }
+
+    for (migration, status) in [
+        (M0 {}.get_id(), MigrationStatus::Success),
+        (M3 {}.get_id(), MigrationStatus::Fail),
+        (M1 {}.get_id(), MigrationStatus::Success),
+    ] {
+        assert_eq!(
+            t.db.collection::<MigrationRecord>("migrations")
+                .find_one(bson::doc! {"_id": migration})
+                .await
+                .unwrap()
+                .unwrap()
+                .status,
+            status
+        );
+    }
+
+    assert!(t
+        .db
+        .collection::<Users>("users")
+        .find_one(bson::doc! {"x": 1})
+        .await
+        .unwrap()
+        .is_some());
+}
@@ -0,0 +1,75 @@
+//! These tests check how migrations are executed up to or down to a target migration
+use bson::Bson;
+use futures::stream::StreamExt;
+use mongodb::Database;
+use mongodb_migrator::{migration::Migration, migration_record::MigrationRecord};
+
+use super::utils::{init_migrator_with_migrations, TestDb, M0, M1, M2};
+
+async fn get_records_ids_ordered_by_end_date(db: &Database) -> Vec<String> {
+    db.collection("migrations")
+        .find(bson::doc! {})
+        .sort(bson::doc! {"end_date": 1})
+        .await
+        .unwrap()
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .map(|v| bson::from_bson(Bson::Document(v.unwrap())).unwrap())
+        .map(|v: MigrationRecord| v._id)
+        .collect::<Vec<String>>()
+}
+
+// M0 -> M1
+pub async fn up_to_executes_migrations_including_target(t: &TestDb) {
+    let migrations: Vec<Box<dyn Migration>> =
+        vec![Box::new(M0 {}), Box::new(M1 {}), Box::new(M2 {})];
+
+    init_migrator_with_migrations(t.db.clone(), migrations)
+        .up_to(M1 {}.get_id().to_string())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        get_records_ids_ordered_by_end_date(&t.db).await,
+        vec![M0 {}.get_id().to_string(), M1 {}.get_id().to_string()]
+    );
+}
+
+// M2 -> M1
+pub async fn down_to_rollbacks_migrations_after_target(t: &TestDb) {
+    let migrations: Vec<Box<dyn Migration>> =
+        vec![Box::new(M0 {}), Box::new(M1 {}), Box::new(M2 {})];
+
+    let migrator = init_migrator_with_migrations(t.db.clone(), migrations);
+    migrator.up().await.unwrap();
+    migrator.down_to(M0 {}.get_id().to_string()).await.unwrap();
+
+    assert_eq!(
+        get_records_ids_ordered_by_end_date(&t.db).await,
+        vec![
+            M0 {}.get_id().to_string(),
+            M2 {}.get_id().to_string(),
+            M1 {}.get_id().to_string()
+        ]
+    );
+}
+
+// M2 -> M1
+pub async fn down_last_rollbacks_passed_count_of_migrations(t: &TestDb) {
+    let migrations: Vec<Box<dyn Migration>> =
+        vec![Box::new(M0 {}), Box::new(M1 {}), Box::new(M2 {})];
+
+    let migrator = init_migrator_with_migrations(t.db.clone(), migrations);
+    migrator.up().await.unwrap();
+    migrator.down_last(2).await.unwrap();
+
+    assert_eq!(
+        get_records_ids_ordered_by_end_date(&t.db).await,
+        vec![
+            M0 {}.get_id().to_string(),
+            M2 {}.get_id().to_string(),
+            M1 {}.get_id().to_string()
+        ]
+    );
+}
@@ -1,13 +1,37 @@
 use utils::TestDb;
 
 mod basic;
+mod builder;
+mod checksum;
+mod consistency;
+mod dependencies;
+mod env;
+mod export;
 mod fail;
+mod history;
+mod lock;
+mod manual;
+mod memory;
 mod migration_trait;
+mod observer;
+mod parallel;
+mod plan;
 mod rerun;
+mod retries;
+mod retry_policy;
+mod rollback;
 mod sequence;
 mod server;
 mod shell;
 mod single_run_migrations;
+mod stale;
+mod state_db;
+mod state_format;
+mod store;
+mod strategy;
+mod targets;
+mod timeout;
+mod transaction;
 mod utils;
 mod validate;
 mod version_numbers;
@@ -25,10 +49,58 @@ async fn run_all_tests() {
     run_test!(basic::basic(&t.node).await);
     run_test!(basic::custom_collection_name(&t.node).await);
 
+    run_test!(builder::settings_passed_in_any_order_reach_runner(&t).await);
+    run_test!(builder::shell_config_and_retries_are_kept_together(&t).await);
+
+    run_test!(checksum::changed_applied_migration_is_reported_according_to_policy(&t).await);
+
+    run_test!(consistency::unknown_records_are_reported_according_to_policy(&t).await);
+    run_test!(
+        consistency::migration_inserted_before_applied_ones_is_reported_according_to_policy(&t)
+            .await
+    );
+
+    run_test!(dependencies::migrations_executed_after_their_dependencies(&t).await);
+    run_test!(dependencies::validation_fails_when_dependency_is_missing(&t).await);
+    run_test!(dependencies::validation_fails_when_dependencies_form_cycle(&t).await);
+
+    run_test!(env::env_contains_ids_client_progress_and_extensions(&t).await);
+    run_test!(env::cancellation_is_signalled_when_timeout_is_elapsed(&t).await);
+
+    run_test!(export::state_is_exported_and_imported_into_another_db(&t).await);
+
     run_test!(fail::with_failed_migration_should_stop_after_first_fail_and_save_failed_with_next_not_executed_as_failed(&t).await);
+    run_test!(fail::failed_migration_keeps_its_error_in_record_and_result(&t).await);
+
+    run_test!(history::every_attempt_is_appended_to_history(&t).await);
+    run_test!(history::run_history_contains_failed_attempt_with_error(&t).await);
+
+    run_test!(lock::concurrent_migrators_execute_migration_once(&t).await);
+    run_test!(lock::fail_fast_when_lock_is_held_and_force_unlock_releases_it(&t).await);
+
+    run_test!(manual::baseline_marks_migrations_as_applied_without_executing(&t).await);
+    run_test!(manual::marked_and_forgotten_migrations_are_executed_by_next_up(&t).await);
+
+    run_test!(observer::observer_is_notified_about_every_step(&t).await);
+
+    run_test!(parallel::migrations_of_the_same_group_overlap_when_enabled(&t).await);
+    run_test!(parallel::migrations_are_executed_one_by_one_by_default(&t).await);
+    run_test!(parallel::failed_migration_lets_its_batch_finish_and_stops_next_ones(&t).await);
+
+    run_test!(plan::plan_explains_decisions_and_executes_nothing(&t).await);
 
     run_test!(rerun::picks_only_failed(&t).await);
 
+    run_test!(retries::failed_migration_succeeds_after_retry(&t).await);
+    run_test!(retries::failed_migration_is_rerun_by_next_run(&t).await);
+    run_test!(retries::record_modified_during_execution_is_not_overwritten(&t).await);
+
+    run_test!(retry_policy::not_transient_error_is_not_retried_by_default_predicate(&t).await);
+    run_test!(retry_policy::migration_retry_policy_overrides_migrator_one(&t).await);
+
+    run_test!(rollback::down_rollbacks_only_applied_migrations(&t).await);
+    run_test!(rollback::rolled_back_migrations_are_executed_by_next_up(&t).await);
+
     run_test!(sequence::migrations_executed_in_specified_order(&t).await);
     run_test!(sequence::all_migrations_have_success_status(&t).await);
     run_test!(sequence::migrations_not_just_saved_as_executed_but_really_affected_target(&t).await);
@@ -39,6 +111,27 @@ async fn run_all_tests() {
     run_test!(single_run_migrations::migrations_executed_in_single_manner(&t).await);
     run_test!(single_run_migrations::down_migrations_executed_in_single_manner(&t).await);
 
+    run_test!(stale::block_policy_stops_until_repaired(&t).await);
+    run_test!(stale::mark_interrupted_policy_reruns_stale_and_skips_alive(&t).await);
+
+    run_test!(state_db::records_of_targets_are_kept_in_state_db(&t).await);
+    run_test!(state_db::state_db_keeps_default_collection_name(&t).await);
+
+    run_test!(state_format::legacy_records_are_upgraded_and_unknown_statuses_are_skipped(&t).await);
+
+    run_test!(store::state_is_kept_by_passed_store(&t).await);
+
+    run_test!(strategy::try_all_executes_migrations_after_failed_one(&t).await);
+
+    run_test!(targets::up_to_executes_migrations_including_target(&t).await);
+    run_test!(targets::down_to_rollbacks_migrations_after_target(&t).await);
+    run_test!(targets::down_last_rollbacks_passed_count_of_migrations(&t).await);
+
+    run_test!(timeout::hung_migration_is_saved_as_timed_out(&t).await);
+    run_test!(timeout::default_timeout_is_applied_and_try_all_continues(&t).await);
+
+    run_test!(transaction::transactional_migration_fails_without_replica_set(&t).await);
+
     run_test!(validate::validation_fails_when_passed_with_duplicates(&t).await);
     run_test!(validate::validation_passes_since_all_unique(&t).await);
 
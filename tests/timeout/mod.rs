@@ -0,0 +1,92 @@
+//! These tests check that hung migrations are cancelled by their timeouts
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use mongodb_migrator::{
+    error::MigrationExecution,
+    migration::Migration,
+    migration_status::MigrationStatus,
+    migrator::{execution_strategy::ExecutionStrategy, Env},
+};
+
+use super::utils::{get_status, init_migrator_with_migrations, TestDb, M0};
+
+struct Hung {}
+
+#[async_trait]
+impl Migration for Hung {
+    async fn up(&self, _env: Env) -> Result<()> {
+        tokio::time::sleep(Duration::from_secs(3600)).await;
+
+        Ok(())
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        Some(Duration::from_millis(100))
+    }
+}
+
+struct HungWithoutTimeout {}
+
+#[async_trait]
+impl Migration for HungWithoutTimeout {
+    async fn up(&self, _env: Env) -> Result<()> {
+        tokio::time::sleep(Duration::from_secs(3600)).await;
+
+        Ok(())
+    }
+}
+
+pub async fn hung_migration_is_saved_as_timed_out(t: &TestDb) {
+    let migrations: Vec<Box<dyn Migration>> = vec![Box::new(Hung {}), Box::new(M0 {})];
+
+    let res = init_migrator_with_migrations(t.db.clone(), migrations)
+        .up()
+        .await;
+
+    match res {
+        Err(MigrationExecution::TimedOut {
+            migration_id,
+            timeout,
+            next_not_executed_migrations_ids,
+        }) => {
+            assert_eq!(migration_id, Hung {}.get_id());
+            assert_eq!(timeout, Duration::from_millis(100));
+            assert_eq!(
+                next_not_executed_migrations_ids,
+                vec![M0 {}.get_id().to_string()]
+            );
+        }
+        _ => unreachable!(),
+    }
+    assert_eq!(
+        get_status(t, Hung {}.get_id()).await,
+        MigrationStatus::TimedOut
+    );
+    assert_eq!(get_status(t, M0 {}.get_id()).await, MigrationStatus::Fail);
+}
+
+pub async fn default_timeout_is_applied_and_try_all_continues(t: &TestDb) {
+    let migrations: Vec<Box<dyn Migration>> =
+        vec![Box::new(HungWithoutTimeout {}), Box::new(M0 {})];
+
+    let res = init_migrator_with_migrations(t.db.clone(), migrations)
+        .set_default_timeout(Duration::from_millis(100))
+        .set_execution_strategy(ExecutionStrategy::TryAll)
+        .up()
+        .await;
+
+    assert!(matches!(
+        res,
+        Err(MigrationExecution::TryAllFinishedWithFailures { .. })
+    ));
+    assert_eq!(
+        get_status(t, HungWithoutTimeout {}.get_id()).await,
+        MigrationStatus::TimedOut
+    );
+    assert_eq!(
+        get_status(t, M0 {}.get_id()).await,
+        MigrationStatus::Success
+    );
+}
@@ -0,0 +1,218 @@
+//! These tests check that writes of transactional migrations are committed together with their records
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use mongodb_migrator::{
+    error::MigrationExecution,
+    migration::Migration,
+    migration_record::MigrationRecord,
+    migration_status::MigrationStatus,
+    migrator::{stale::StaleConfig, store::MemoryMigrationStore, Env, Migrator},
+};
+use testcontainers_modules::{mongo::Mongo, testcontainers::runners::AsyncRunner};
+
+use super::utils::{init_migrator_with_migrations, TestDb, M0};
+
+/// Inserts a user within the transaction, waits for `delay` and fails afterwards if `fail` is set
+#[derive(Default)]
+struct InsertsUser {
+    fail: bool,
+    delay: Duration,
+}
+
+#[async_trait]
+impl Migration for InsertsUser {
+    async fn up(&self, env: Env) -> Result<()> {
+        let session = env.session.expect("transactional migration has a session");
+        let mut session = session.lock().await;
+
+        env.db
+            .expect("db is available")
+            .collection::<bson::Document>("users")
+            .insert_one(bson::doc! {"x": 0})
+            .session(&mut *session)
+            .await?;
+
+        tokio::time::sleep(self.delay).await;
+
+        if self.fail {
+            anyhow::bail!("failed after insert");
+        }
+
+        Ok(())
+    }
+
+    fn transactional(&self) -> bool {
+        true
+    }
+}
+
+pub async fn transactional_migration_fails_without_replica_set(t: &TestDb) {
+    let migrations: Vec<Box<dyn Migration>> = vec![Box::new(InsertsUser::default())];
+
+    let res = init_migrator_with_migrations(t.db.clone(), migrations)
+        .up()
+        .await;
+
+    match res {
+        Err(MigrationExecution::TransactionsNotSupported { migration_id, .. }) => {
+            assert_eq!(migration_id, InsertsUser::default().get_id());
+        }
+        _ => unreachable!(),
+    }
+    assert_eq!(
+        t.db.collection::<bson::Document>("users")
+            .count_documents(bson::doc! {})
+            .await
+            .unwrap(),
+        0
+    );
+}
+
+#[tokio::test]
+pub async fn transactional_migration_writes_are_committed_only_with_success() {
+    let node = Mongo::repl_set().start().await.unwrap();
+    let host_port = node.get_host_port_ipv4(27017).await.unwrap();
+    let url = format!("mongodb://localhost:{}/?directConnection=true", host_port);
+    let client = mongodb::Client::with_uri_str(url).await.unwrap();
+    let db = client.database("test");
+    let get_record = || async {
+        db.collection::<MigrationRecord>("migrations")
+            .find_one(bson::doc! {"_id": InsertsUser::default().get_id()})
+            .await
+            .unwrap()
+            .unwrap()
+    };
+    let count_users = || async {
+        db.collection::<bson::Document>("users")
+            .count_documents(bson::doc! {})
+            .await
+            .unwrap()
+    };
+
+    let res = init_migrator_with_migrations(
+        db.clone(),
+        vec![Box::new(InsertsUser {
+            fail: true,
+            ..Default::default()
+        })],
+    )
+    .up()
+    .await;
+
+    assert!(res.is_err());
+    assert_eq!(count_users().await, 0);
+    let record = get_record().await;
+    assert_eq!(record.status, MigrationStatus::Fail);
+    assert_eq!(
+        record.error.map(|error| error.message),
+        Some("failed after insert".to_string())
+    );
+
+    let res = init_migrator_with_migrations(db.clone(), vec![Box::new(InsertsUser::default())])
+        .up()
+        .await;
+
+    assert!(res.is_ok());
+    assert_eq!(count_users().await, 1);
+    assert_eq!(get_record().await.status, MigrationStatus::Success);
+}
+
+#[tokio::test]
+pub async fn transactional_migration_outlives_heartbeat_interval() {
+    let node = Mongo::repl_set().start().await.unwrap();
+    let host_port = node.get_host_port_ipv4(27017).await.unwrap();
+    let url = format!("mongodb://localhost:{}/?directConnection=true", host_port);
+    let client = mongodb::Client::with_uri_str(url).await.unwrap();
+    let db = client.database("test");
+    let migration = InsertsUser {
+        delay: Duration::from_millis(500),
+        ..Default::default()
+    };
+    let migration_id = migration.get_id().to_string();
+
+    let res = init_migrator_with_migrations(db.clone(), vec![Box::new(migration)])
+        .set_stale_config(StaleConfig {
+            heartbeat_interval: Duration::from_millis(50),
+            ..Default::default()
+        })
+        .up()
+        .await;
+
+    assert!(res.is_ok());
+    assert_eq!(
+        db.collection::<MigrationRecord>("migrations")
+            .find_one(bson::doc! {"_id": migration_id})
+            .await
+            .unwrap()
+            .unwrap()
+            .status,
+        MigrationStatus::Success
+    );
+}
+
+#[tokio::test]
+pub async fn transactional_migration_fails_with_custom_store() {
+    let client = mongodb::Client::with_uri_str("mongodb://localhost:27017")
+        .await
+        .unwrap();
+    let store = MemoryMigrationStore::new();
+
+    let res = Migrator::builder()
+        .with_conn(client.database("test"))
+        .with_store(store.clone())
+        .build(vec![Box::new(InsertsUser::default()), Box::new(M0 {})])
+        .up()
+        .await;
+
+    match res {
+        Err(MigrationExecution::TransactionsNotSupportedByStore { migration_id, .. }) => {
+            assert_eq!(migration_id, InsertsUser::default().get_id());
+        }
+        _ => unreachable!(),
+    }
+    // the failed start of the transaction is saved like other failed attempts
+    let records = store.records();
+    let record = &records[InsertsUser::default().get_id()];
+    assert_eq!(record.status, MigrationStatus::Fail);
+    assert!(record.error.is_some());
+    assert_eq!(records[M0 {}.get_id()].status, MigrationStatus::Fail);
+    let history = store.history();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].status, MigrationStatus::Fail);
+}
+
+#[tokio::test]
+pub async fn transactional_migration_fails_with_state_db_of_another_client() {
+    let node = Mongo::repl_set().start().await.unwrap();
+    let host_port = node.get_host_port_ipv4(27017).await.unwrap();
+    let url = format!("mongodb://localhost:{}/?directConnection=true", host_port);
+    let db = mongodb::Client::with_uri_str(&url)
+        .await
+        .unwrap()
+        .database("test");
+    let state_db = mongodb::Client::with_uri_str(&url)
+        .await
+        .unwrap()
+        .database("state");
+
+    let res = init_migrator_with_migrations(db.clone(), vec![Box::new(InsertsUser::default())])
+        .set_state_db(state_db)
+        .up()
+        .await;
+
+    match res {
+        Err(MigrationExecution::TransactionsNotSupportedByStore { migration_id, .. }) => {
+            assert_eq!(migration_id, InsertsUser::default().get_id());
+        }
+        _ => unreachable!(),
+    }
+    assert_eq!(
+        db.collection::<bson::Document>("users")
+            .count_documents(bson::doc! {})
+            .await
+            .unwrap(),
+        0
+    );
+}
@@ -1,9 +1,16 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
 use anyhow::Result;
 use async_trait::async_trait;
 use mongodb::Database;
 use mongodb_migrator::{
     migration::Migration,
-    migrator::{shell::ShellConfig, Env},
+    migration_record::MigrationRecord,
+    migration_status::MigrationStatus,
+    migrator::{shell::ShellConfig, with_retries::RetryPolicy, Env},
 };
 use serde_derive::{Deserialize, Serialize};
 use testcontainers_modules::{
@@ -50,6 +57,17 @@ pub fn init_shell_migrator_with_migrations(
         .with_migrations_vec(migrations)
 }
 
+pub async fn get_record(t: &TestDb, migration_id: &str) -> Option<MigrationRecord> {
+    t.db.collection::<MigrationRecord>("migrations")
+        .find_one(bson::doc! {"_id": migration_id})
+        .await
+        .unwrap()
+}
+
+pub async fn get_status(t: &TestDb, migration_id: &str) -> MigrationStatus {
+    get_record(t, migration_id).await.unwrap().status
+}
+
 pub struct M0 {}
 pub struct M1 {}
 pub struct M2 {}
@@ -123,3 +141,26 @@ impl Migration for M3 {
         Err(anyhow::Error::msg("test error".to_string()))
     }
 }
+
+/// Fails `fails_count` first attempts with a non transient error
+#[derive(Default)]
+pub struct FailsFirstAttempts {
+    pub attempts: Arc<AtomicUsize>,
+    pub fails_count: usize,
+    pub retry_policy: Option<RetryPolicy>,
+}
+
+#[async_trait]
+impl Migration for FailsFirstAttempts {
+    async fn up(&self, _env: Env) -> Result<()> {
+        if self.attempts.fetch_add(1, Ordering::SeqCst) < self.fails_count {
+            anyhow::bail!("attempt failed");
+        }
+
+        Ok(())
+    }
+
+    fn retry_policy(&self) -> Option<RetryPolicy> {
+        self.retry_policy.clone()
+    }
+}